@@ -29,9 +29,9 @@ pub trait CreateEmbedExt {
 
     fn success_box<T: ToString>(text: T) -> Self;
 
-    fn fields_chunked_fmt<'a, T, F>(
+    fn fields_chunked_fmt<T, F>(
         &mut self,
-        content: &'a [T],
+        content: &[T],
         fmt: F,
         title: &str,
         inline: bool,
@@ -40,9 +40,9 @@ pub trait CreateEmbedExt {
     where
         F: Fn(&T) -> String;
 
-    fn fields_chunked<'a, T>(
+    fn fields_chunked<T>(
         &mut self,
-        content: &'a [T],
+        content: &[T],
         title: &str,
         inline: bool,
         count: usize,
@@ -67,9 +67,9 @@ impl CreateEmbedExt for CreateEmbed {
         _text_box(text)
     }
 
-    fn fields_chunked_fmt<'a, T, F>(
+    fn fields_chunked_fmt<T, F>(
         &mut self,
-        content: &'a [T],
+        content: &[T],
         fmt: F,
         title: &str,
         inline: bool,
@@ -87,9 +87,9 @@ impl CreateEmbedExt for CreateEmbed {
         self
     }
 
-    fn fields_chunked<'a, T>(
+    fn fields_chunked<T>(
         &mut self,
-        content: &'a [T],
+        content: &[T],
         title: &str,
         inline: bool,
         count: usize,
@@ -1,41 +1,141 @@
 mod component_interaction_collector;
+mod parsers;
+#[cfg(feature = "cache")]
+mod pickers;
+mod poll;
+mod reactions;
+mod reply;
+mod selector;
+mod session;
+mod signup;
+mod store;
+mod update_scheduler;
+mod wizard;
 
 use std::{
     collections::{HashMap, HashSet},
     fmt::Display,
     hash::Hash,
-    time::Duration,
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
 };
 
-pub use component_interaction_collector::MessageCollectorExt;
+pub use component_interaction_collector::{ComponentEventCollector, MessageCollectorExt};
+#[cfg(feature = "cache")]
+pub use parsers::{ChannelParser, MemberParser, RoleParser};
+pub use parsers::{
+    ColourParser, DateTimeParser, DurationParser, FloatParser, IntegerParser, ValueParser,
+};
+#[cfg(feature = "cache")]
+pub use pickers::{ChannelPickerFilter, MemberPickerFilter, PickerItem, RolePickerFilter};
+pub use poll::{PollConfig, PollError, PollInput, PollResult, PollState};
+pub use reactions::ReactionConfig;
+pub use reply::{
+    AttachmentFilter, ReceivedAttachment, ReplyCollectorExt, ReplyError, ReplyPromptConfig,
+};
+pub use selector::{
+    GroupedSelection, GroupedSelectorState, PagedSelectorState, QuantitySelectorState,
+    RankedSelectorState, SelectorEvent, SelectorState,
+};
+pub use session::{
+    Session, SessionError, SessionInfo, SessionKind, SessionPolicy, SessionRegistry, SessionScope,
+};
+pub use signup::{SignupConfig, SignupEvent, SignupRole, SignupRoster, SignupState};
+pub use store::{
+    resume_sessions, FileSessionStore, MemorySessionStore, SessionStore, StoredSession,
+};
+pub use tokio_util::sync::CancellationToken;
+pub use update_scheduler::UpdateScheduler;
+pub use wizard::{Wizard, WizardAnswer, WizardAnswers, WizardError, WizardStep};
 
 use serenity::{
-    builder::{CreateActionRow, CreateButton, CreateEmbed},
+    builder::{CreateActionRow, CreateEmbed},
     client::Context,
-    futures::StreamExt,
+    collector::ReactionAction,
+    futures::{future::pending, stream::BoxStream, StreamExt},
+    json::{json, JsonMap, Value},
     model::{
-        channel::ReactionType,
-        interactions::{
-            application_command::ApplicationCommandInteraction,
-            message_component::{ButtonStyle, MessageComponentInteraction},
+        application::{
+            component::ButtonStyle,
+            interaction::{
+                message_component::MessageComponentInteraction, modal::ModalSubmitInteraction,
+            },
         },
+        channel::ReactionType,
+        id::{ChannelId, MessageId, UserId, WebhookId},
         prelude::Message,
     },
     Error as SerenityError, Result as SerenityResult,
 };
-use tokio::{select, time::sleep};
-
-use crate::{
-    builder::CreateActionRowExt, components::Button, interactions::MessageComponentInteractionExt,
+use tokio::{
+    select,
+    time::{sleep, Instant},
 };
 
-// Since ephemeral Messages cant be updated through Message
-// this is a bit of a work around.
-// Still need the message for the collector
-pub enum UpdatAbleMessage<'a> {
-    Message(&'a mut Message),
-    ApplicationCommand(&'a ApplicationCommandInteraction, &'a mut Message),
-    ComponentInteraction(&'a MessageComponentInteraction, &'a mut Message),
+use crate::{builder::CreateEmbedExt, components::Button};
+use store::Persistence;
+
+/// A message collectors can edit, wherever it came from. Owns everything
+/// needed to edit the message, so it can be cloned and moved into
+/// spawned tasks. Interaction tokens are only valid for 15 minutes.
+#[derive(Clone, Debug)]
+pub enum UpdatAbleMessage {
+    /// A channel message of the bot
+    Message(Message),
+    /// The original response to an interaction of any kind, including
+    /// ephemeral ones
+    InteractionResponse { token: String, message: Message },
+    /// A followup message of an interaction
+    Followup { token: String, message: Message },
+    /// A message sent through a webhook
+    Webhook {
+        id: WebhookId,
+        token: String,
+        message: Message,
+    },
+}
+
+impl From<Message> for UpdatAbleMessage {
+    fn from(msg: Message) -> Self {
+        Self::Message(msg)
+    }
+}
+
+/// What happens to the message once a selector stopped waiting for input
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ExpiryBehaviour {
+    /// Leave the message as is
+    Keep,
+    /// Keep the components but disable them
+    DisableComponents,
+    /// Remove all components
+    RemoveComponents,
+    /// Replace the message with an info box with this text
+    InfoBox(String),
+}
+
+/// Bulk actions that can be added to the paged selector
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SelectorControl {
+    /// Select every item on the current page
+    SelectPage,
+    /// Select every item on all pages
+    SelectAll,
+    /// Deselect everything
+    ClearAll,
+    /// Select everything that is not selected and vice versa
+    Invert,
+}
+
+impl SelectorControl {
+    fn button(&self) -> Button {
+        match self {
+            Self::SelectPage => Button::SelectPage,
+            Self::SelectAll => Button::SelectAll,
+            Self::ClearAll => Button::ClearAll,
+            Self::Invert => Button::Invert,
+        }
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -49,10 +149,29 @@ pub struct PagedSelectorConfig<'a, T> {
     rows_pages: usize,
     // Gets reset after every input
     timeout: Duration,
+    // Does not get reset
+    deadline: Option<Duration>,
+    max_interactions: Option<usize>,
+    expiry: ExpiryBehaviour,
+    show_expiry: bool,
     // minimum selection required
     min: usize,
+    // maximum selection allowed
+    max: Option<usize>,
     // pre selected values when
     pre_selected: Option<&'a [&'a T]>,
+    // bulk actions next to confirm and abort
+    controls: Vec<SelectorControl>,
+    // item button look depending on the selection
+    selected_style: ButtonStyle,
+    unselected_style: ButtonStyle,
+    // overrides the emoji returned for the item if set
+    selected_button_emoji: Option<ReactionType>,
+    unselected_button_emoji: Option<ReactionType>,
+    // stops the selector from outside
+    cancel_token: Option<CancellationToken>,
+    // stores the progress to resume it
+    persistence: Option<Persistence>,
 }
 
 impl<T> Default for PagedSelectorConfig<'_, T> {
@@ -64,8 +183,20 @@ impl<T> Default for PagedSelectorConfig<'_, T> {
             items_rows: 5,
             rows_pages: 4,
             timeout: Duration::from_secs(60),
+            deadline: None,
+            max_interactions: None,
+            expiry: ExpiryBehaviour::DisableComponents,
+            show_expiry: false,
             min: 0,
+            max: None,
             pre_selected: None,
+            controls: Vec::new(),
+            selected_style: ButtonStyle::Success,
+            unselected_style: ButtonStyle::Primary,
+            selected_button_emoji: None,
+            unselected_button_emoji: None,
+            cancel_token: None,
+            persistence: None,
         }
     }
 }
@@ -101,21 +232,225 @@ impl<'a, T> PagedSelectorConfig<'a, T> {
         self
     }
 
+    /// Time after which the selector ends no matter how active the user is
+    pub fn deadline(&mut self, deadline: Duration) -> &mut Self {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    /// Number of clicks after which the selector ends if it is not done
+    pub fn max_interactions(&mut self, count: usize) -> &mut Self {
+        self.max_interactions = Some(count);
+        self
+    }
+
+    /// What happens to the message on timeout, deadline or interaction
+    /// limit. Disables the components by default.
+    pub fn expiry(&mut self, expiry: ExpiryBehaviour) -> &mut Self {
+        self.expiry = expiry;
+        self
+    }
+
+    /// Adds a relative timestamp of when the selector expires to the embed
+    pub fn show_expiry(&mut self, show: bool) -> &mut Self {
+        self.show_expiry = show;
+        self
+    }
+
     pub fn min_select(&mut self, min: usize) -> &mut Self {
         self.min = min;
         self
     }
 
+    pub fn max_select(&mut self, max: usize) -> &mut Self {
+        self.max = Some(max);
+        self
+    }
+
     pub fn pre_selected(&mut self, pre_selected: &'a [&'a T]) -> &mut Self {
         self.pre_selected = Some(pre_selected);
         self
     }
+
+    /// Adds bulk action buttons to the last row. If they do not fit next to
+    /// confirm, abort, next and previous they get their own row, which leaves
+    /// at most 3 rows per page for items.
+    pub fn controls(&mut self, controls: &[SelectorControl]) -> &mut Self {
+        self.controls = controls.to_vec();
+        self
+    }
+
+    pub fn selected_style(&mut self, style: ButtonStyle) -> &mut Self {
+        self.selected_style = style;
+        self
+    }
+
+    pub fn unselected_style(&mut self, style: ButtonStyle) -> &mut Self {
+        self.unselected_style = style;
+        self
+    }
+
+    /// Emoji on the item button while selected instead of the one returned
+    /// for the item
+    pub fn selected_button_emoji(&mut self, emoji: ReactionType) -> &mut Self {
+        self.selected_button_emoji = Some(emoji);
+        self
+    }
+
+    /// Emoji on the item button while not selected instead of the one
+    /// returned for the item
+    pub fn unselected_button_emoji(&mut self, emoji: ReactionType) -> &mut Self {
+        self.unselected_button_emoji = Some(emoji);
+        self
+    }
+
+    /// Stops the selector with [`PagedSelectorError::Cancelled`] once the
+    /// token gets cancelled, e.g. by a newer invocation of the command.
+    pub fn cancel_token(&mut self, token: CancellationToken) -> &mut Self {
+        self.cancel_token = Some(token);
+        self
+    }
+
+    /// Saves the progress after every input, so the selector can be
+    /// resumed on the same message after a restart. `kind` tells the bot
+    /// which selector to rebuild, see [`resume_sessions`]. The progress is
+    /// kept if the cancel token stops the selector.
+    pub fn persist<K: ToString>(&mut self, store: Arc<dyn SessionStore>, kind: K) -> &mut Self {
+        self.persistence = Some(Persistence {
+            store,
+            kind: kind.to_string(),
+        });
+        self
+    }
+
+    /// The limits to run a selector state built from this config with.
+    /// Settings without a setter here, like the edit interval, can be
+    /// changed on them before [`UpdatAbleMessage::run_selector`].
+    pub fn limits(&self) -> SelectorLimits {
+        SelectorLimits {
+            timeout: self.timeout,
+            deadline: self.deadline,
+            max_interactions: self.max_interactions,
+            expiry: self.expiry.clone(),
+            show_expiry: self.show_expiry,
+            cancel_token: self.cancel_token.clone(),
+            persistence: self.persistence.clone(),
+            ..Default::default()
+        }
+    }
+}
+
+/// When [`UpdatAbleMessage::run_selector`] stops waiting for the user
+#[derive(Clone, Debug)]
+pub struct SelectorLimits {
+    // Gets reset after every input
+    timeout: Duration,
+    deadline: Option<Duration>,
+    max_interactions: Option<usize>,
+    expiry: ExpiryBehaviour,
+    show_expiry: bool,
+    edit_interval: Duration,
+    cancel_token: Option<CancellationToken>,
+    persistence: Option<Persistence>,
+}
+
+impl Default for SelectorLimits {
+    fn default() -> Self {
+        Self {
+            timeout: Duration::from_secs(60),
+            deadline: None,
+            max_interactions: None,
+            expiry: ExpiryBehaviour::DisableComponents,
+            show_expiry: false,
+            edit_interval: Duration::from_millis(250),
+            cancel_token: None,
+            persistence: None,
+        }
+    }
+}
+
+impl SelectorLimits {
+    pub fn timeout(&mut self, timeout: Duration) -> &mut Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn deadline(&mut self, deadline: Duration) -> &mut Self {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    pub fn max_interactions(&mut self, count: usize) -> &mut Self {
+        self.max_interactions = Some(count);
+        self
+    }
+
+    pub fn expiry(&mut self, expiry: ExpiryBehaviour) -> &mut Self {
+        self.expiry = expiry;
+        self
+    }
+
+    pub fn show_expiry(&mut self, show: bool) -> &mut Self {
+        self.show_expiry = show;
+        self
+    }
+
+    /// Minimum time between two edits of the message. Clicks in between
+    /// are combined into one edit.
+    pub fn edit_interval(&mut self, interval: Duration) -> &mut Self {
+        self.edit_interval = interval;
+        self
+    }
+
+    pub fn cancel_token(&mut self, token: CancellationToken) -> &mut Self {
+        self.cancel_token = Some(token);
+        self
+    }
+
+    pub fn persist<K: ToString>(&mut self, store: Arc<dyn SessionStore>, kind: K) -> &mut Self {
+        self.persistence = Some(Persistence {
+            store,
+            kind: kind.to_string(),
+        });
+        self
+    }
+}
+
+// Relative timestamp of the unix time the prompt expires at
+fn expiry_field(emb: &mut CreateEmbed, expires: Duration) {
+    emb.field(
+        "\u{200b}",
+        format!("expires <t:{}:R>", expires.as_secs()),
+        false,
+    );
+}
+
+/// Sets every component in the rows to disabled
+pub fn disable_components(rows: &mut [CreateActionRow]) {
+    for row in rows {
+        if let Some(Value::Array(components)) = row.0.get_mut("components") {
+            for component in components {
+                if let Value::Object(component) = component {
+                    component.insert("disabled".to_string(), Value::Bool(true));
+                }
+            }
+        }
+    }
 }
 
 #[derive(Debug)]
 pub enum PagedSelectorError {
+    /// No input within the timeout
     TimedOut,
+    /// The deadline passed
+    DeadlineExceeded,
+    /// The maximum number of interactions was reached
+    InteractionLimitReached,
     Aborted,
+    /// The cancel token was cancelled
+    Cancelled,
+    /// The session store failed
+    Store(std::io::Error),
     Serenity(serenity::Error),
 }
 
@@ -123,7 +458,13 @@ impl Display for PagedSelectorError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Self::TimedOut => write!(f, "Paged Selector timed out"),
+            Self::DeadlineExceeded => write!(f, "Paged Selector exceeded its deadline"),
+            Self::InteractionLimitReached => {
+                write!(f, "Paged Selector reached its interaction limit")
+            }
             Self::Aborted => write!(f, "Paged Selector was aborted"),
+            Self::Cancelled => write!(f, "Paged Selector was cancelled"),
+            Self::Store(e) => e.fmt(f),
             Self::Serenity(e) => e.fmt(f),
         }
     }
@@ -131,19 +472,182 @@ impl Display for PagedSelectorError {
 
 impl std::error::Error for PagedSelectorError {}
 
+impl From<std::io::Error> for PagedSelectorError {
+    fn from(e: std::io::Error) -> Self {
+        Self::Store(e)
+    }
+}
+
 impl From<SerenityError> for PagedSelectorError {
     fn from(e: SerenityError) -> Self {
         Self::Serenity(e)
     }
 }
 
-impl<'a> UpdatAbleMessage<'a> {
+fn unix_now() -> Duration {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+}
+
+/// Resolves once the token is cancelled, never without a token
+pub(crate) async fn cancelled(token: Option<&CancellationToken>) {
+    match token {
+        Some(token) => token.cancelled().await,
+        None => pending().await,
+    }
+}
+
+/// The component interactions on the message. Injected ones take the
+/// place of the gateway when testing.
+pub(crate) async fn component_interactions(
+    ctx: &Context,
+    msg: &Message,
+) -> BoxStream<'static, Arc<MessageComponentInteraction>> {
+    #[cfg(any(test, feature = "testing"))]
+    if let Some(injected) = ctx
+        .data
+        .read()
+        .await
+        .get::<crate::testing::InjectedInteractions>()
+    {
+        return injected.listen(msg.id.0).boxed();
+    }
+    msg.await_component_interactions(ctx).build().boxed()
+}
+
+/// The modal submissions of modals opened from the message. Injected ones
+/// take the place of the gateway when testing.
+pub(crate) async fn modal_submissions(
+    ctx: &Context,
+    msg: &Message,
+) -> BoxStream<'static, Arc<ModalSubmitInteraction>> {
+    #[cfg(any(test, feature = "testing"))]
+    if let Some(injected) = ctx
+        .data
+        .read()
+        .await
+        .get::<crate::testing::InjectedModals>()
+    {
+        return injected.listen(msg.id.0).boxed();
+    }
+    msg.await_modal_interactions(ctx).build().boxed()
+}
+
+/// The messages of the user in the channel. Injected ones take the place
+/// of the gateway when testing.
+pub(crate) async fn channel_messages(
+    ctx: &Context,
+    channel_id: ChannelId,
+    author_id: UserId,
+) -> BoxStream<'static, Arc<Message>> {
+    #[cfg(any(test, feature = "testing"))]
+    if let Some(injected) = ctx
+        .data
+        .read()
+        .await
+        .get::<crate::testing::InjectedMessages>()
+    {
+        return injected
+            .listen(channel_id.0)
+            .filter(move |m| serenity::futures::future::ready(m.author.id == author_id))
+            .boxed();
+    }
+    channel_id
+        .await_replies(ctx)
+        .author_id(author_id)
+        .build()
+        .boxed()
+}
+
+/// The reactions of the user on the message, added and removed ones.
+/// Injected ones take the place of the gateway when testing.
+pub(crate) async fn message_reactions(
+    ctx: &Context,
+    msg: &Message,
+    author_id: UserId,
+) -> BoxStream<'static, Arc<ReactionAction>> {
+    #[cfg(any(test, feature = "testing"))]
+    if let Some(injected) = ctx
+        .data
+        .read()
+        .await
+        .get::<crate::testing::InjectedReactions>()
+    {
+        return injected
+            .listen(msg.id.0)
+            .filter(move |r| {
+                serenity::futures::future::ready(r.as_inner_ref().user_id == Some(author_id))
+            })
+            .boxed();
+    }
+    msg.await_reactions(ctx)
+        .author_id(author_id)
+        .added(true)
+        .removed(true)
+        .build()
+        .boxed()
+}
+
+/// The embeds and components of a message edit
+pub(crate) fn edit_payload(embeds: &[CreateEmbed], rows: &[CreateActionRow]) -> JsonMap {
+    let mut map = JsonMap::new();
+    map.insert(
+        "embeds".to_string(),
+        json!(embeds.iter().map(|e| &e.0).collect::<Vec<_>>()),
+    );
+    map.insert(
+        "components".to_string(),
+        json!(rows.iter().map(|r| &r.0).collect::<Vec<_>>()),
+    );
+    map
+}
+
+impl UpdatAbleMessage {
+    /// Fetches the channel message
+    pub async fn fetch(
+        ctx: &Context,
+        channel_id: ChannelId,
+        message_id: MessageId,
+    ) -> SerenityResult<Self> {
+        Ok(Self::Message(channel_id.message(ctx, message_id).await?))
+    }
+
+    /// Fetches the original response of the interaction with the token,
+    /// e.g. after responding to a command, component or modal submit
+    pub async fn interaction_response<T: ToString>(
+        ctx: &Context,
+        token: T,
+    ) -> SerenityResult<Self> {
+        let token = token.to_string();
+        let message = ctx.http.get_original_interaction_response(&token).await?;
+        Ok(Self::InteractionResponse { token, message })
+    }
+
+    /// A followup message created with the interaction token
+    pub fn followup<T: ToString>(token: T, message: Message) -> Self {
+        Self::Followup {
+            token: token.to_string(),
+            message,
+        }
+    }
+
+    /// A message sent through the webhook with the token
+    pub fn webhook<T: ToString>(id: WebhookId, token: T, message: Message) -> Self {
+        Self::Webhook {
+            id,
+            token: token.to_string(),
+            message,
+        }
+    }
+
     pub async fn update(
         &mut self,
         ctx: &Context,
         embeds: Vec<CreateEmbed>,
         ars: Vec<CreateActionRow>,
     ) -> SerenityResult<()> {
+        let payload = edit_payload(&embeds, &ars);
         match self {
             Self::Message(msg) => {
                 msg.edit(ctx, |m| {
@@ -152,196 +656,322 @@ impl<'a> UpdatAbleMessage<'a> {
                 })
                 .await
             }
-            Self::ApplicationCommand(aci, _) => {
-                aci.edit_original_interaction_response(ctx, |m| {
-                    m.set_embeds(embeds);
-                    m.components(|c| c.set_action_rows(ars))
-                })
-                .await?;
+            Self::InteractionResponse { token, message } => {
+                *message = ctx
+                    .http
+                    .edit_original_interaction_response(token, &Value::Object(payload))
+                    .await?;
                 Ok(())
             }
-            Self::ComponentInteraction(mci, _) => {
-                mci.edit_original_interaction_response(ctx, |m| {
-                    m.set_embeds(embeds);
-                    m.components(|c| c.set_action_rows(ars))
-                })
-                .await?;
+            Self::Followup { token, message } => {
+                *message = ctx
+                    .http
+                    .edit_followup_message(token, message.id.0, &Value::Object(payload))
+                    .await?;
+                Ok(())
+            }
+            Self::Webhook { id, token, message } => {
+                *message = ctx
+                    .http
+                    .edit_webhook_message(id.0, token, message.id.0, &payload)
+                    .await?;
                 Ok(())
             }
         }
     }
 
+    /// Fetches the current state of the message
+    pub async fn refresh(&mut self, ctx: &Context) -> SerenityResult<()> {
+        match self {
+            Self::Message(msg) => *msg = msg.channel_id.message(ctx, msg.id).await?,
+            Self::InteractionResponse { token, message } => {
+                *message = ctx.http.get_original_interaction_response(token).await?
+            }
+            Self::Followup { token, message } => {
+                *message = ctx.http.get_followup_message(token, message.id.0).await?
+            }
+            Self::Webhook { id, token, message } => {
+                *message = ctx
+                    .http
+                    .get_webhook_message(id.0, token, message.id.0)
+                    .await?
+            }
+        }
+        Ok(())
+    }
+
     pub fn msg(&self) -> &Message {
         match self {
             Self::Message(msg) => msg,
-            Self::ApplicationCommand(_, msg) => msg,
-            Self::ComponentInteraction(_, msg) => msg,
+            Self::InteractionResponse { message, .. } => message,
+            Self::Followup { message, .. } => message,
+            Self::Webhook { message, .. } => message,
+        }
+    }
+
+    pub fn into_msg(self) -> Message {
+        match self {
+            Self::Message(msg) => msg,
+            Self::InteractionResponse { message, .. } => message,
+            Self::Followup { message, .. } => message,
+            Self::Webhook { message, .. } => message,
         }
     }
 
-    pub fn release(self) {}
+    /// Removes the components and tells the user the collector was
+    /// cancelled
+    pub async fn cancel(&mut self, ctx: &Context) -> SerenityResult<()> {
+        let info = CreateEmbed::info_box("This was cancelled");
+        self.update(ctx, vec![info], Vec::new()).await
+    }
 
-    pub async fn paged_selector<'b, 'c, T, F>(
-        &'c mut self,
+    /// Applies the expiry behaviour to the last shown embeds and rows
+    async fn expire(
+        &mut self,
         ctx: &Context,
-        config: PagedSelectorConfig<'b, T>,
-        values: &'b [T],
-        button: F,
-    ) -> Result<HashSet<&'b T>, PagedSelectorError>
-    where
-        T: Display + Eq + Hash + Send + Sync,
-        F: Fn(&T) -> (ReactionType, String) + Send + Sync,
-    {
-        let mut mapping: HashMap<String, &T> = HashMap::with_capacity(values.len());
-        let mut curr_page: usize = 0;
-
-        let paged_components = {
-            // We can have up to 5 Buttons for each action row
-            let value_chunks: Vec<_> = values.chunks(config.items_rows).collect();
-            // Total of 4 rows available for selection. Rest is confirm, abort, ...
-            let row_chunks = value_chunks.chunks(config.rows_pages);
-            // Create Action Rows
-            let mut pages: Vec<Vec<CreateActionRow>> = Vec::with_capacity(row_chunks.len());
-            for rows in row_chunks {
-                let mut new_page = Vec::new();
-                for row in rows {
-                    let mut ar = CreateActionRow::default();
-                    for b in row.iter() {
-                        let (emoji, button_title) = button(b);
-                        let mut button = CreateButton::default();
-                        let custom_id = format!("_tools_selector_{}", &button_title);
-                        button
-                            .emoji(emoji)
-                            .label(&button_title)
-                            .style(ButtonStyle::Primary)
-                            .custom_id(&custom_id);
-
-                        mapping.insert(custom_id, b);
-
-                        ar.add_button(button);
-                    }
-                    new_page.push(ar);
-                }
-                pages.push(new_page);
+        expiry: &ExpiryBehaviour,
+        embeds: Vec<CreateEmbed>,
+        mut rows: Vec<CreateActionRow>,
+    ) -> SerenityResult<()> {
+        match expiry {
+            ExpiryBehaviour::Keep => Ok(()),
+            ExpiryBehaviour::DisableComponents => {
+                disable_components(&mut rows);
+                self.update(ctx, embeds, rows).await
             }
-            pages
-        };
-
-        if paged_components.is_empty() {
-            return Ok(HashSet::new());
-        }
-
-        // keep track of what is selected
-        let mut selected: HashSet<&T> = HashSet::new();
-        if let Some(pre_sel) = config.pre_selected {
-            for s in pre_sel {
-                selected.insert(s);
+            ExpiryBehaviour::RemoveComponents => self.update(ctx, embeds, Vec::new()).await,
+            ExpiryBehaviour::InfoBox(text) => {
+                let info = CreateEmbed::info_box(text);
+                self.update(ctx, vec![info], Vec::new()).await
             }
         }
+    }
 
-        let emb = vec![paged_selector_embed(&config, values, &selected, curr_page)];
-        let mut ar = paged_components.get(curr_page).unwrap().to_vec();
-        let mut sar = CreateActionRow::default();
-        let mut conf_button = Button::Confirm.create();
-        if selected.len() < config.min {
-            conf_button.disabled(true);
-        }
-        sar.add_button(conf_button).abort_button();
-        if curr_page > 0 {
-            sar.prev_button();
+    /// Drives the selector state with the component interactions on
+    /// this message until it is done or a limit is hit. The timeout gets
+    /// reset after every interaction. Cancelling the token cleans up the
+    /// message, the other limits apply the expiry behaviour. With
+    /// persistence the stored progress on this message is restored first
+    /// and a resumed selector keeps its original deadline.
+    pub async fn run_selector<S: SelectorState>(
+        &mut self,
+        ctx: &Context,
+        mut state: S,
+        limits: &SelectorLimits,
+    ) -> Result<S::Output, PagedSelectorError> {
+        let mut deadline_at = limits.deadline.map(|d| unix_now() + d);
+        let persistence = match &limits.persistence {
+            Some(p) => p,
+            None => return self.drive_selector(ctx, state, limits, deadline_at).await,
+        };
+        if let Some(saved) = persistence.load(self.msg())? {
+            state.restore(&saved["state"]);
+            if let (Some(_), Some(at)) = (deadline_at, saved["deadline_at"].as_u64()) {
+                deadline_at = Some(Duration::from_millis(at));
+            }
         }
-        if curr_page < paged_components.len() - 1 {
-            sar.next_button();
+        let res = self.drive_selector(ctx, state, limits, deadline_at).await;
+        // Keep the progress if only Discord could not be reached or the
+        // token got cancelled, e.g. on shutdown
+        if !matches!(
+            res,
+            Err(PagedSelectorError::Serenity(_) | PagedSelectorError::Cancelled)
+        ) {
+            persistence.remove(self.msg())?;
         }
-        ar.push(sar);
-        self.update(ctx, emb, ar).await?;
+        res
+    }
+
+    // `deadline_at` is the unix time the deadline passes at
+    async fn drive_selector<S: SelectorState>(
+        &mut self,
+        ctx: &Context,
+        mut state: S,
+        limits: &SelectorLimits,
+        deadline_at: Option<Duration>,
+    ) -> Result<S::Output, PagedSelectorError> {
+        let save = |msg: &Message, state: &S| match &limits.persistence {
+            Some(p) => p.save(
+                msg,
+                json!({
+                    "state": state.save(),
+                    "deadline_at": deadline_at.map(|d| d.as_millis() as u64),
+                }),
+            ),
+            None => Ok(()),
+        };
+        let render = |state: &S| {
+            let (mut emb, ar) = state.render();
+            if let (true, Some(e)) = (limits.show_expiry, emb.last_mut()) {
+                let timeout_at = unix_now() + limits.timeout;
+                let expires = deadline_at.map_or(timeout_at, |d| d.min(timeout_at));
+                expiry_field(e, expires);
+            }
+            (emb, ar)
+        };
 
-        let mut interactions = self.msg().await_component_interactions(ctx).build();
+        let mut updates = UpdateScheduler::new(limits.edit_interval);
+        let (emb, ar) = render(&state);
+        updates.schedule(emb.clone(), ar.clone());
+        updates.flush(ctx, self).await?;
+        let mut shown = (emb, ar);
+        save(self.msg(), &state)?;
+
+        let mut interactions = component_interactions(ctx, self.msg()).await;
+        let cancelled = cancelled(limits.cancel_token.as_ref());
+        tokio::pin!(cancelled);
+        let deadline = async {
+            match deadline_at {
+                Some(at) => sleep(at.saturating_sub(unix_now())).await,
+                None => pending().await,
+            }
+        };
+        tokio::pin!(deadline);
+        let idle = sleep(limits.timeout);
+        tokio::pin!(idle);
+        let mut count = 0;
 
-        loop {
+        let err = loop {
             // using select instead of collector timeout to reset
             // timeout after button click
             select! {
+                // queued clicks get handled before the message is edited
+                biased;
+
                 react = interactions.next() => {
                     // Should always be some
                     let react = react.unwrap();
 
-                    match react.parse_button() {
-                        // a default button
-                        Ok(b) => match b {
-                            Button::Confirm => {
-                                react.defer(ctx).await?;
-                                return Ok(selected);
-                            },
-                            Button::Abort => {
-                                react.defer(ctx).await?;
-                                return Err(PagedSelectorError::Aborted);
-                            },
-                            Button::Next => curr_page += 1,
-                            Button::Previous => curr_page -= 1,
-                        },
-                        // Selected an item
-                        Err(_) => {
-                            let selected_t = mapping.get(&react.data.custom_id).unwrap();
-                            if !selected.remove(selected_t) { selected.insert(selected_t); };
+                    let done = state.handle(&SelectorEvent::from(&*react));
+                    count += 1;
+                    idle.as_mut().reset(Instant::now() + limits.timeout);
+                    match done {
+                        Some(res) => {
+                            react.defer(ctx).await?;
+                            return res;
+                        }
+                        None if limits.max_interactions.is_some_and(|max| count >= max) => {
+                            react.defer(ctx).await?;
+                            break PagedSelectorError::InteractionLimitReached;
+                        }
+                        None => {
+                            save(self.msg(), &state)?;
+                            let (emb, ar) = render(&state);
+                            updates.schedule(emb.clone(), ar.clone());
+                            shown = (emb, ar);
+                            // one request instead of acknowledging and editing
+                            updates.respond(ctx, &react).await?;
                         }
                     }
-
-                    let emb = vec![paged_selector_embed(&config, values, &selected, curr_page)];
-                    let mut ar = paged_components.get(curr_page).unwrap().to_vec();
-                    let mut sar = CreateActionRow::default();
-                    let mut conf_button = Button::Confirm.create();
-                    if selected.len() < config.min {
-                        conf_button.disabled(true);
-                    }
-                    sar.add_button(conf_button).abort_button();
-                    if curr_page > 0 {
-                        sar.prev_button();
-                    }
-                    if curr_page < paged_components.len() - 1 {
-                        sar.next_button();
-                    }
-                    ar.push(sar);
-                    react.defer(ctx).await?;
-                    self.update(ctx, emb, ar).await?;
                 },
-                _ = sleep(config.timeout) => return Err(PagedSelectorError::TimedOut),
+                _ = updates.ready(), if updates.is_pending() => {
+                    updates.flush(ctx, self).await?;
+                },
+                _ = &mut idle => break PagedSelectorError::TimedOut,
+                _ = &mut deadline => break PagedSelectorError::DeadlineExceeded,
+                _ = &mut cancelled => {
+                    self.cancel(ctx).await?;
+                    return Err(PagedSelectorError::Cancelled);
+                },
             }
+        };
+
+        self.expire(ctx, &limits.expiry, shown.0, shown.1).await?;
+        Err(err)
+    }
+
+    pub async fn paged_selector<'b, T, F>(
+        &mut self,
+        ctx: &Context,
+        config: PagedSelectorConfig<'b, T>,
+        values: &'b [T],
+        button: F,
+    ) -> Result<HashSet<&'b T>, PagedSelectorError>
+    where
+        T: Display + Eq + Hash + Send + Sync,
+        F: Fn(&T) -> (ReactionType, String) + Send + Sync,
+    {
+        if values.is_empty() {
+            return Ok(HashSet::new());
         }
+        let limits = config.limits();
+        let state = PagedSelectorState::new(config, values, button);
+        self.run_selector(ctx, state, &limits).await
     }
-}
 
-fn paged_selector_embed<T: Display + Eq + Hash>(
-    config: &PagedSelectorConfig<T>,
-    values: &[T],
-    selected: &HashSet<&T>,
-    curr_page: usize,
-) -> CreateEmbed {
-    let mut emb = config.base_embed.clone();
-    let role_fields = values.chunks(config.items_rows * config.rows_pages);
-    for (i, e) in role_fields.enumerate() {
-        emb.field(
-            format!(
-                "Page {}{}",
-                i + 1,
-                if i == curr_page { " (current)" } else { "" }
-            ),
-            e.iter()
-                .map(|t| {
-                    format!(
-                        "{} | {}",
-                        if selected.contains(t) {
-                            &config.selected_emoji
-                        } else {
-                            &config.unselected_emoj
-                        },
-                        t
-                    )
-                })
-                .collect::<Vec<_>>()
-                .join("\n"),
-            true,
-        );
+    /// Lets the user pick items in order. The position of each picked item
+    /// is shown in the embed instead of the selected emoji. Clicking a
+    /// picked item again removes it from the ranking. Bulk controls are
+    /// not available, the last row holds an undo button instead.
+    pub async fn ranked_selector<'b, T, F>(
+        &mut self,
+        ctx: &Context,
+        config: PagedSelectorConfig<'b, T>,
+        values: &'b [T],
+        button: F,
+    ) -> Result<Vec<&'b T>, PagedSelectorError>
+    where
+        T: Display + Eq + Hash + Send + Sync,
+        F: Fn(&T) -> (ReactionType, String) + Send + Sync,
+    {
+        if values.is_empty() {
+            return Ok(Vec::new());
+        }
+        let limits = config.limits();
+        let state = RankedSelectorState::new(config, values, button);
+        self.run_selector(ctx, state, &limits).await
+    }
+
+    /// Lets the user pick how many of each item they want. Clicking an item
+    /// focuses it and shows buttons to change its quantity within the
+    /// bounds returned by `bounds`. Items start at their lower bound, pre
+    /// selected ones at least at 1. The min and max selection of the config
+    /// apply to the number of items with a quantity above 0. Bulk controls
+    /// are not available and at most 3 rows per page are used for items.
+    pub async fn quantity_selector<'b, T, F, B>(
+        &mut self,
+        ctx: &Context,
+        config: PagedSelectorConfig<'b, T>,
+        values: &'b [T],
+        button: F,
+        bounds: B,
+    ) -> Result<HashMap<&'b T, u32>, PagedSelectorError>
+    where
+        T: Display + Eq + Hash + Send + Sync,
+        F: Fn(&T) -> (ReactionType, String) + Send + Sync,
+        B: Fn(&T) -> (u32, u32) + Send + Sync,
+    {
+        if values.is_empty() {
+            return Ok(HashMap::new());
+        }
+        let limits = config.limits();
+        let state = QuantitySelectorState::new(config, values, button, bounds);
+        self.run_selector(ctx, state, &limits).await
+    }
+
+    /// Same as the paged selector but keeps every group on its own pages.
+    /// A select menu allows to jump between the groups, so at most 3 rows
+    /// per page are used for items and only the first 25 groups can be
+    /// jumped to.
+    pub async fn grouped_paged_selector<'b, T, F>(
+        &mut self,
+        ctx: &Context,
+        config: PagedSelectorConfig<'b, T>,
+        groups: &'b [(String, Vec<T>)],
+        button: F,
+    ) -> Result<GroupedSelection<'b, T>, PagedSelectorError>
+    where
+        T: Display + Eq + Hash + Send + Sync,
+        F: Fn(&T) -> (ReactionType, String) + Send + Sync,
+    {
+        let limits = config.limits();
+        let mut state = GroupedSelectorState::new(config, groups, button);
+        if groups.iter().all(|(_, vs)| vs.is_empty()) {
+            // Confirm is always possible without anything to select
+            if let Some(res) = state.handle(&SelectorEvent::button(Button::Confirm)) {
+                return res;
+            }
+        }
+        self.run_selector(ctx, state, &limits).await
     }
-    emb
 }
@@ -1,20 +1,38 @@
+use std::{marker::PhantomData, str::FromStr, sync::Arc, time::Duration};
+
 use serenity::{
-    client::bridge::gateway::ShardMessenger, collector::CollectComponentInteraction,
-    model::channel::Message,
+    client::{bridge::gateway::ShardMessenger, Context},
+    collector::CollectComponentInteraction,
+    futures::{
+        stream::{self, BoxStream},
+        StreamExt,
+    },
+    model::{
+        application::interaction::message_component::MessageComponentInteraction, channel::Message,
+        id::UserId,
+    },
 };
+use tokio::time::timeout;
+
+use crate::interactions::MessageComponentInteractionExt;
 
 pub trait MessageCollectorExt {
     /// This already filters for confirm and abort buttons only
     fn await_confirm_abort_interaction(
         &self,
-        shard_messenger: & impl AsRef<ShardMessenger>,
+        shard_messenger: &impl AsRef<ShardMessenger>,
     ) -> CollectComponentInteraction;
+
+    /// Decodes the custom id of every component interaction on the
+    /// message into `E`, e.g. a [`Button`](crate::components::Button) or
+    /// an enum of the bot.
+    fn component_events<E: FromStr>(&self, ctx: &Context) -> ComponentEventCollector<E>;
 }
 
 impl MessageCollectorExt for Message {
     fn await_confirm_abort_interaction(
         &self,
-        shard_messenger: & impl AsRef<ShardMessenger>,
+        shard_messenger: &impl AsRef<ShardMessenger>,
     ) -> CollectComponentInteraction {
         self.await_component_interaction(shard_messenger)
             .filter(|mci| {
@@ -24,4 +42,104 @@ impl MessageCollectorExt for Message {
                 )
             })
     }
+
+    fn component_events<E: FromStr>(&self, ctx: &Context) -> ComponentEventCollector<E> {
+        ComponentEventCollector {
+            ctx: ctx.clone(),
+            msg: self.clone(),
+            author_id: None,
+            timeout: None,
+            report_unknown: false,
+            event: PhantomData,
+        }
+    }
+}
+
+/// Builds a stream of decoded component interactions, see
+/// [`MessageCollectorExt::component_events`]
+pub struct ComponentEventCollector<E> {
+    ctx: Context,
+    msg: Message,
+    author_id: Option<UserId>,
+    // idle time after which the stream ends
+    timeout: Option<Duration>,
+    report_unknown: bool,
+    event: PhantomData<fn() -> E>,
+}
+
+impl<E> ComponentEventCollector<E>
+where
+    E: FromStr + Send + 'static,
+{
+    /// Only interactions of this user are yielded, the clicks of others
+    /// are only acknowledged
+    pub fn author_id(mut self, author_id: UserId) -> Self {
+        self.author_id = Some(author_id);
+        self
+    }
+
+    /// Ends the stream once no event was yielded for this long
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Answers interactions whose custom id can not be decoded and the
+    /// clicks of other users with an ephemeral error instead of dropping
+    /// them silently
+    pub fn report_unknown(mut self, report: bool) -> Self {
+        self.report_unknown = report;
+        self
+    }
+
+    /// Starts collecting right away, interactions before the stream is
+    /// polled the first time are kept
+    pub async fn build(self) -> BoxStream<'static, (E, MessageComponentInteraction)> {
+        let interactions = super::component_interactions(&self.ctx, &self.msg).await;
+        stream::unfold(
+            (self, interactions),
+            |(this, mut interactions)| async move {
+                let next = this.next(&mut interactions);
+                let event = match this.timeout {
+                    Some(idle) => timeout(idle, next).await.ok().flatten()?,
+                    None => next.await?,
+                };
+                Some((event, (this, interactions)))
+            },
+        )
+        .boxed()
+    }
+
+    async fn next(
+        &self,
+        interactions: &mut BoxStream<'static, Arc<MessageComponentInteraction>>,
+    ) -> Option<(E, MessageComponentInteraction)> {
+        loop {
+            let mci = interactions.next().await?;
+            if self.author_id.is_some_and(|a| a != mci.user.id) {
+                // Unanswered clicks show an error to the user
+                let _ = match self.report_unknown {
+                    true => {
+                        mci.create_quick_error(&self.ctx, "This is not for you", true)
+                            .await
+                    }
+                    false => mci.defer(&self.ctx).await,
+                };
+                continue;
+            }
+            match mci.data.custom_id.parse().ok() {
+                Some(event) => {
+                    let mci = Arc::try_unwrap(mci).unwrap_or_else(|mci| (*mci).clone());
+                    return Some((event, mci));
+                }
+                None if self.report_unknown => {
+                    // The user can retry, the stream goes on regardless
+                    let _ = mci
+                        .create_quick_error(&self.ctx, "Unknown action", true)
+                        .await;
+                }
+                None => (),
+            }
+        }
+    }
 }
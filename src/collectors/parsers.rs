@@ -0,0 +1,507 @@
+use std::time::Duration;
+
+use serenity::{model::Timestamp, utils::Colour};
+
+#[cfg(feature = "cache")]
+pub use mentions::{ChannelParser, MemberParser, RoleParser};
+
+/// Turns the text of a reply into a value. The error is shown to the user,
+/// see [`ReplyCollectorExt::await_value`](super::ReplyCollectorExt::await_value).
+pub trait ValueParser {
+    type Output;
+
+    fn parse(&self, text: &str) -> Result<Self::Output, String>;
+}
+
+fn check_range<T: PartialOrd>(
+    value: T,
+    min: Option<T>,
+    max: Option<T>,
+    fmt: impl Fn(&T) -> String,
+) -> Result<T, String> {
+    match (min, max) {
+        (Some(min), Some(max)) if value < min || value > max => Err(format!(
+            "Expected a value between {} and {}",
+            fmt(&min),
+            fmt(&max)
+        )),
+        (Some(min), _) if value < min => Err(format!("Expected at least {}", fmt(&min))),
+        (_, Some(max)) if value > max => Err(format!("Expected at most {}", fmt(&max))),
+        _ => Ok(value),
+    }
+}
+
+/// Whole numbers like `42` or `-7`
+#[derive(Clone, Debug, Default)]
+pub struct IntegerParser {
+    min: Option<i64>,
+    max: Option<i64>,
+}
+
+impl IntegerParser {
+    pub fn min(&mut self, min: i64) -> &mut Self {
+        self.min = Some(min);
+        self
+    }
+
+    pub fn max(&mut self, max: i64) -> &mut Self {
+        self.max = Some(max);
+        self
+    }
+}
+
+impl ValueParser for IntegerParser {
+    type Output = i64;
+
+    fn parse(&self, text: &str) -> Result<i64, String> {
+        let value = text
+            .trim()
+            .parse()
+            .map_err(|_| "Expected a whole number".to_string())?;
+        check_range(value, self.min, self.max, i64::to_string)
+    }
+}
+
+/// Numbers like `3.5`, a decimal comma works as well
+#[derive(Clone, Debug, Default)]
+pub struct FloatParser {
+    min: Option<f64>,
+    max: Option<f64>,
+}
+
+impl FloatParser {
+    pub fn min(&mut self, min: f64) -> &mut Self {
+        self.min = Some(min);
+        self
+    }
+
+    pub fn max(&mut self, max: f64) -> &mut Self {
+        self.max = Some(max);
+        self
+    }
+}
+
+impl ValueParser for FloatParser {
+    type Output = f64;
+
+    fn parse(&self, text: &str) -> Result<f64, String> {
+        let value = text
+            .trim()
+            .replace(',', ".")
+            .parse::<f64>()
+            .ok()
+            .filter(|v| v.is_finite())
+            .ok_or_else(|| "Expected a number".to_string())?;
+        check_range(value, self.min, self.max, f64::to_string)
+    }
+}
+
+/// Durations like `1h30m`, `2d 12h` or `90s`. Units are `w`, `d`, `h`,
+/// `m` or `min` and `s`.
+#[derive(Clone, Debug, Default)]
+pub struct DurationParser {
+    min: Option<Duration>,
+    max: Option<Duration>,
+}
+
+impl DurationParser {
+    pub fn min(&mut self, min: Duration) -> &mut Self {
+        self.min = Some(min);
+        self
+    }
+
+    pub fn max(&mut self, max: Duration) -> &mut Self {
+        self.max = Some(max);
+        self
+    }
+}
+
+// Formats a duration the way it gets parsed
+fn format_duration(duration: &Duration) -> String {
+    let mut secs = duration.as_secs();
+    if secs == 0 {
+        return "0s".to_string();
+    }
+    let mut text = String::new();
+    for (unit, len) in [("d", 86_400), ("h", 3_600), ("m", 60), ("s", 1)] {
+        if secs >= len {
+            text.push_str(&format!("{}{}", secs / len, unit));
+            secs %= len;
+        }
+    }
+    text
+}
+
+impl ValueParser for DurationParser {
+    type Output = Duration;
+
+    fn parse(&self, text: &str) -> Result<Duration, String> {
+        let invalid = || "Expected a duration like 1h30m".to_string();
+        let text = text.trim().to_lowercase();
+        let mut rest = text.as_str();
+        let mut secs: u64 = 0;
+        if rest.is_empty() {
+            return Err(invalid());
+        }
+        while !rest.is_empty() {
+            let digits = rest
+                .find(|c: char| !c.is_ascii_digit())
+                .ok_or_else(invalid)?;
+            let count: u64 = rest[..digits].parse().map_err(|_| invalid())?;
+            rest = rest[digits..].trim_start();
+            let unit_len = rest
+                .find(|c: char| !c.is_ascii_alphabetic())
+                .unwrap_or(rest.len());
+            let unit = match &rest[..unit_len] {
+                "w" => 604_800,
+                "d" => 86_400,
+                "h" => 3_600,
+                "m" | "min" => 60,
+                "s" => 1,
+                _ => return Err(invalid()),
+            };
+            secs = count
+                .checked_mul(unit)
+                .and_then(|s| s.checked_add(secs))
+                .ok_or_else(invalid)?;
+            rest = rest[unit_len..].trim_start();
+        }
+        check_range(
+            Duration::from_secs(secs),
+            self.min,
+            self.max,
+            format_duration,
+        )
+    }
+}
+
+/// Dates like `2024-05-01`, optionally with a time like `18:30` or
+/// `18:30:15` and an offset like `+02:00`, `-0500`, `Z` or `UTC`. Without
+/// an offset the configured one is used. Named time zones are not known.
+#[derive(Clone, Debug, Default)]
+pub struct DateTimeParser {
+    // minutes east of UTC
+    utc_offset: i32,
+}
+
+impl DateTimeParser {
+    /// Offset for dates given without one, in minutes east of UTC
+    pub fn utc_offset(&mut self, minutes: i32) -> &mut Self {
+        self.utc_offset = minutes;
+        self
+    }
+}
+
+// Days since 1970-01-01 of the proleptic gregorian date
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = if year >= 0 { year } else { year - 399 } / 400;
+    let yoe = year - era * 400;
+    let doy = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+fn days_in_month(year: i64, month: i64) -> i64 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+// Minutes east of UTC of an offset like `+02:00`, `-0500` or `+2`
+fn parse_offset(offset: &str) -> Option<i32> {
+    let sign = match offset.chars().next()? {
+        '+' => 1,
+        '-' => -1,
+        _ => return None,
+    };
+    let digits = offset[1..].replace(':', "");
+    let (hours, minutes) = match digits.len() {
+        1 | 2 => (digits.as_str(), "0"),
+        4 => digits.split_at(2),
+        _ => return None,
+    };
+    let (hours, minutes) = (hours.parse::<i32>().ok()?, minutes.parse::<i32>().ok()?);
+    (hours <= 14 && minutes < 60).then(|| sign * (hours * 60 + minutes))
+}
+
+impl ValueParser for DateTimeParser {
+    type Output = Timestamp;
+
+    fn parse(&self, text: &str) -> Result<Timestamp, String> {
+        let invalid = || "Expected a date like 2024-05-01 18:30 +02:00".to_string();
+        let text = text.trim();
+
+        let (text, offset) = if let Some(t) = text.strip_suffix(['Z', 'z']) {
+            (t, 0)
+        } else if let Some(t) = text
+            .get(..text.len().saturating_sub(3))
+            .filter(|_| text.to_lowercase().ends_with("utc"))
+        {
+            (t, 0)
+        } else {
+            // The date itself contains dashes, an offset comes after it
+            match text.get(10..).and_then(|t| t.rfind(['+', '-'])) {
+                Some(i) => {
+                    let (t, offset) = text.split_at(10 + i);
+                    (t, parse_offset(offset.trim()).ok_or_else(invalid)?)
+                }
+                None => (text, self.utc_offset),
+            }
+        };
+
+        let text = text.trim();
+        let (date, time) = match text.split_once(['T', ' ']) {
+            Some((date, time)) => (date, time.trim()),
+            None => (text, "00:00"),
+        };
+
+        let date = date
+            .split('-')
+            .map(|p| p.parse::<i64>().ok())
+            .collect::<Option<Vec<_>>>()
+            .ok_or_else(invalid)?;
+        // Larger years would overflow the timestamp math
+        let (year, month, day) = match date.as_slice() {
+            [y, m, d]
+                if (1..=9999).contains(y)
+                    && (1..=12).contains(m)
+                    && (1..=days_in_month(*y, *m)).contains(d) =>
+            {
+                (*y, *m, *d)
+            }
+            _ => return Err(invalid()),
+        };
+        let time = time
+            .split(':')
+            .map(|p| p.parse::<i64>().ok())
+            .collect::<Option<Vec<_>>>()
+            .ok_or_else(invalid)?;
+        let secs = match time.as_slice() {
+            [h, m] if *h < 24 && *m < 60 => h * 3_600 + m * 60,
+            [h, m, s] if *h < 24 && *m < 60 && *s < 60 => h * 3_600 + m * 60 + s,
+            _ => return Err(invalid()),
+        };
+
+        let unix = days_from_civil(year, month, day) * 86_400 + secs - offset as i64 * 60;
+        Timestamp::from_unix_timestamp(unix).map_err(|_| invalid())
+    }
+}
+
+/// Colours like `#ff8800`, `f80`, `0xff8800`, `255, 136, 0` or
+/// `rgb(255, 136, 0)`
+#[derive(Clone, Debug, Default)]
+pub struct ColourParser;
+
+impl ValueParser for ColourParser {
+    type Output = Colour;
+
+    fn parse(&self, text: &str) -> Result<Colour, String> {
+        let invalid = || "Expected a colour like #ff8800 or 255, 136, 0".to_string();
+        let text = text.trim().to_lowercase();
+
+        if text.contains(',') {
+            let rgb = text
+                .trim_start_matches("rgb")
+                .trim_start_matches('(')
+                .trim_end_matches(')')
+                .split(',')
+                .map(|c| c.trim().parse::<u8>().ok())
+                .collect::<Option<Vec<_>>>()
+                .ok_or_else(invalid)?;
+            return match rgb.as_slice() {
+                [r, g, b] => Ok(Colour::from_rgb(*r, *g, *b)),
+                _ => Err(invalid()),
+            };
+        }
+
+        let hex = text.trim_start_matches('#').trim_start_matches("0x");
+        if !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(invalid());
+        }
+        let hex = match hex.len() {
+            3 => hex.chars().flat_map(|c| [c, c]).collect(),
+            6 => hex.to_string(),
+            _ => return Err(invalid()),
+        };
+        u32::from_str_radix(&hex, 16)
+            .map(Colour::new)
+            .map_err(|_| invalid())
+    }
+}
+
+#[cfg(feature = "cache")]
+mod mentions {
+    use std::sync::Arc;
+
+    use serenity::{
+        cache::Cache,
+        model::{
+            channel::{Channel, GuildChannel},
+            guild::{Member, Role},
+            id::GuildId,
+        },
+        utils::{parse_channel, parse_role, parse_username},
+    };
+
+    use super::ValueParser;
+
+    // The id of a mention like `<@1>` or a plain id
+    fn id(text: &str, parse: fn(&str) -> Option<u64>) -> Option<u64> {
+        parse(text).or_else(|| text.parse().ok())
+    }
+
+    /// Members of the guild by mention, id, name or nickname
+    #[derive(Clone)]
+    pub struct MemberParser {
+        cache: Arc<Cache>,
+        guild_id: GuildId,
+    }
+
+    impl MemberParser {
+        pub fn new(cache: Arc<Cache>, guild_id: GuildId) -> Self {
+            Self { cache, guild_id }
+        }
+    }
+
+    impl ValueParser for MemberParser {
+        type Output = Member;
+
+        fn parse(&self, text: &str) -> Result<Member, String> {
+            let text = text.trim();
+            let found = match id(text, |t| parse_username(t)) {
+                Some(id) => self.cache.member(self.guild_id, id),
+                None => self
+                    .cache
+                    .guild_field(self.guild_id, |g| {
+                        g.members
+                            .values()
+                            .find(|m| {
+                                m.user.name.eq_ignore_ascii_case(text)
+                                    || m.user.tag().eq_ignore_ascii_case(text)
+                                    || m.nick
+                                        .as_ref()
+                                        .is_some_and(|n| n.eq_ignore_ascii_case(text))
+                            })
+                            .cloned()
+                    })
+                    .flatten(),
+            };
+            found.ok_or_else(|| format!("No member {} on this server", text))
+        }
+    }
+
+    /// Roles of the guild by mention, id or name
+    #[derive(Clone)]
+    pub struct RoleParser {
+        cache: Arc<Cache>,
+        guild_id: GuildId,
+    }
+
+    impl RoleParser {
+        pub fn new(cache: Arc<Cache>, guild_id: GuildId) -> Self {
+            Self { cache, guild_id }
+        }
+    }
+
+    impl ValueParser for RoleParser {
+        type Output = Role;
+
+        fn parse(&self, text: &str) -> Result<Role, String> {
+            let text = text.trim();
+            let found = match id(text, |t| parse_role(t)) {
+                Some(id) => self.cache.role(self.guild_id, id),
+                None => self
+                    .cache
+                    .guild_field(self.guild_id, |g| {
+                        g.roles
+                            .values()
+                            .find(|r| r.name.eq_ignore_ascii_case(text.trim_start_matches('@')))
+                            .cloned()
+                    })
+                    .flatten(),
+            };
+            found.ok_or_else(|| format!("No role {} on this server", text))
+        }
+    }
+
+    /// Channels of the guild by mention, id or name
+    #[derive(Clone)]
+    pub struct ChannelParser {
+        cache: Arc<Cache>,
+        guild_id: GuildId,
+    }
+
+    impl ChannelParser {
+        pub fn new(cache: Arc<Cache>, guild_id: GuildId) -> Self {
+            Self { cache, guild_id }
+        }
+    }
+
+    impl ValueParser for ChannelParser {
+        type Output = GuildChannel;
+
+        fn parse(&self, text: &str) -> Result<GuildChannel, String> {
+            let text = text.trim();
+            let found = match id(text, |t| parse_channel(t)) {
+                Some(id) => self
+                    .cache
+                    .guild_channel(id)
+                    .filter(|c| c.guild_id == self.guild_id),
+                None => self
+                    .cache
+                    .guild_field(self.guild_id, |g| {
+                        g.channels.values().find_map(|c| match c {
+                            Channel::Guild(c)
+                                if c.name.eq_ignore_ascii_case(text.trim_start_matches('#')) =>
+                            {
+                                Some(c.clone())
+                            }
+                            _ => None,
+                        })
+                    })
+                    .flatten(),
+            };
+            found.ok_or_else(|| format!("No channel {} on this server", text))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn values() {
+        let mut int = IntegerParser::default();
+        int.min(1).max(10);
+        assert_eq!(int.parse(" 7 "), Ok(7));
+        assert!(int.parse("11").is_err());
+        assert!(FloatParser::default().parse("nan").is_err());
+        assert_eq!(FloatParser::default().parse("2,5"), Ok(2.5));
+
+        let duration = DurationParser::default();
+        assert_eq!(duration.parse("1h30m"), Ok(Duration::from_secs(5_400)));
+        assert_eq!(duration.parse("2d 5 min"), Ok(Duration::from_secs(173_100)));
+        assert!(duration.parse("5").is_err());
+        assert_eq!(format_duration(&Duration::from_secs(5_400)), "1h30m");
+
+        let date = DateTimeParser::default();
+        let unix = |t: &str| date.parse(t).map(|t| t.unix_timestamp());
+        assert_eq!(unix("2024-03-01"), Ok(1_709_251_200));
+        assert_eq!(unix("2024-03-01 01:30 +01:30"), Ok(1_709_251_200));
+        assert_eq!(unix("2024-02-29T18:00:00Z"), Ok(1_709_229_600));
+        assert!(unix("2023-02-29").is_err());
+        assert!(unix("99999999999999-01-01").is_err());
+
+        assert_eq!(ColourParser.parse("#f80"), Ok(Colour::new(0xff8800)));
+        assert_eq!(
+            ColourParser.parse("rgb(255, 136, 0)"),
+            Ok(Colour::new(0xff8800))
+        );
+        assert!(ColourParser.parse("#ff88").is_err());
+    }
+}
@@ -0,0 +1,465 @@
+//! Pickers for roles, channels and members of a guild.
+//!
+//! Discord's native user, role, channel and mentionable select menus can
+//! not be used with serenity 0.11, messages containing them fail to
+//! deserialize. The pickers therefore list the guild from the cache in a
+//! [`UpdatAbleMessage::paged_selector`], so the guild has to be cached.
+
+use std::{collections::HashSet, fmt::Display};
+
+use serenity::{
+    client::Context,
+    model::{
+        channel::{Channel, ChannelType, ReactionType},
+        guild::Guild,
+        id::{ChannelId, GuildId, RoleId, UserId},
+        Permissions,
+    },
+};
+
+use super::{PagedSelectorConfig, PagedSelectorError, UpdatAbleMessage};
+
+/// A role, channel or member in a picker, shown as its mention
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum PickerItem {
+    Role(RoleId),
+    Channel(ChannelId),
+    User(UserId),
+}
+
+impl Display for PickerItem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Role(id) => write!(f, "<@&{}>", id),
+            Self::Channel(id) => write!(f, "<#{}>", id),
+            Self::User(id) => write!(f, "<@{}>", id),
+        }
+    }
+}
+
+// Guild wide permissions of the member, without channel overwrites
+fn member_permissions(guild: &Guild, user: UserId) -> Permissions {
+    if guild.owner_id == user {
+        return Permissions::all();
+    }
+    let member = match guild.members.get(&user) {
+        Some(member) => member,
+        None => return Permissions::empty(),
+    };
+    let everyone = RoleId(guild.id.0);
+    let permissions = member
+        .roles
+        .iter()
+        .chain([&everyone])
+        .filter_map(|r| guild.roles.get(r))
+        .fold(Permissions::empty(), |p, r| p | r.permissions);
+    match permissions.administrator() {
+        true => Permissions::all(),
+        false => permissions,
+    }
+}
+
+/// Which roles [`UpdatAbleMessage::role_picker`] lists. `@everyone` is
+/// never listed.
+#[derive(Clone, Debug, Default)]
+pub struct RolePickerFilter {
+    below_bot: bool,
+    managed: bool,
+    permissions: Option<Permissions>,
+}
+
+impl RolePickerFilter {
+    /// Only roles below the highest role of the bot, which it can assign
+    pub fn below_bot(&mut self, below_bot: bool) -> &mut Self {
+        self.below_bot = below_bot;
+        self
+    }
+
+    /// Lists roles managed by integrations and bots as well
+    pub fn managed(&mut self, managed: bool) -> &mut Self {
+        self.managed = managed;
+        self
+    }
+
+    /// Only roles granting all of the permissions
+    pub fn permissions(&mut self, permissions: Permissions) -> &mut Self {
+        self.permissions = Some(permissions);
+        self
+    }
+
+    fn items(&self, guild: &Guild, bot: UserId) -> Vec<(PickerItem, String)> {
+        let bot_position = guild
+            .members
+            .get(&bot)
+            .into_iter()
+            .flat_map(|m| &m.roles)
+            .filter_map(|r| guild.roles.get(r))
+            .map(|r| r.position)
+            .max()
+            .unwrap_or_default();
+
+        let mut roles = guild
+            .roles
+            .values()
+            .filter(|r| r.id.0 != guild.id.0)
+            .filter(|r| self.managed || !r.managed)
+            .filter(|r| !self.below_bot || r.position < bot_position)
+            .filter(|r| self.permissions.is_none_or(|p| r.permissions.contains(p)))
+            .collect::<Vec<_>>();
+        roles.sort_by_key(|r| std::cmp::Reverse(r.position));
+        roles
+            .into_iter()
+            .map(|r| (PickerItem::Role(r.id), r.name.clone()))
+            .collect()
+    }
+}
+
+/// Which channels [`UpdatAbleMessage::channel_picker`] lists. Categories
+/// are never listed, only channels that messages can be sent to or users
+/// can join.
+#[derive(Clone, Debug, Default)]
+pub struct ChannelPickerFilter {
+    // all kinds if empty
+    kinds: Vec<ChannelType>,
+    permissions: Option<(UserId, Permissions)>,
+}
+
+impl ChannelPickerFilter {
+    /// Only channels of these kinds
+    pub fn kinds(&mut self, kinds: &[ChannelType]) -> &mut Self {
+        self.kinds = kinds.to_vec();
+        self
+    }
+
+    /// Only channels in which the user has all of the permissions, e.g.
+    /// the bot or the user invoking the command
+    pub fn permissions(&mut self, user: UserId, permissions: Permissions) -> &mut Self {
+        self.permissions = Some((user, permissions));
+        self
+    }
+
+    fn items(&self, guild: &Guild) -> Vec<(PickerItem, String)> {
+        let mut channels = guild
+            .channels
+            .values()
+            // categories are a channel variant of their own
+            .filter_map(|c| match c {
+                Channel::Guild(c) => Some(c),
+                _ => None,
+            })
+            .filter(|c| self.kinds.is_empty() || self.kinds.contains(&c.kind))
+            .filter(|c| match self.permissions {
+                Some((user, permissions)) => guild
+                    .members
+                    .get(&user)
+                    .and_then(|m| guild.user_permissions_in(c, m).ok())
+                    .is_some_and(|p| p.contains(permissions)),
+                None => true,
+            })
+            .collect::<Vec<_>>();
+        channels.sort_by_key(|c| (c.position, c.id));
+        channels
+            .into_iter()
+            .map(|c| (PickerItem::Channel(c.id), format!("#{}", c.name)))
+            .collect()
+    }
+}
+
+/// Which members [`UpdatAbleMessage::member_picker`] lists
+#[derive(Clone, Debug, Default)]
+pub struct MemberPickerFilter {
+    bots: bool,
+    permissions: Option<Permissions>,
+}
+
+impl MemberPickerFilter {
+    /// Lists bots as well
+    pub fn bots(&mut self, bots: bool) -> &mut Self {
+        self.bots = bots;
+        self
+    }
+
+    /// Only members with all of the permissions in the guild
+    pub fn permissions(&mut self, permissions: Permissions) -> &mut Self {
+        self.permissions = Some(permissions);
+        self
+    }
+
+    fn items(&self, guild: &Guild) -> Vec<(PickerItem, String)> {
+        let mut members = guild
+            .members
+            .values()
+            .filter(|m| self.bots || !m.user.bot)
+            .filter(|m| {
+                self.permissions
+                    .is_none_or(|p| member_permissions(guild, m.user.id).contains(p))
+            })
+            .collect::<Vec<_>>();
+        // Members sharing a name keep a stable order
+        members.sort_by_key(|m| (m.display_name().to_lowercase(), m.user.id));
+        members
+            .into_iter()
+            .map(|m| (PickerItem::User(m.user.id), m.display_name().into_owned()))
+            .collect()
+    }
+}
+
+// Buttons show the names, the selector tells items apart by their index
+fn labels<'a>(
+    items: &'a [(PickerItem, String)],
+    emoji: &'a str,
+) -> impl Fn(&PickerItem) -> (ReactionType, String) + Send + Sync + 'a {
+    move |item| {
+        let name = items
+            .iter()
+            .find(|(i, _)| i == item)
+            .map(|(_, name)| name.chars().take(80).collect())
+            .unwrap_or_default();
+        (ReactionType::Unicode(emoji.to_string()), name)
+    }
+}
+
+impl UpdatAbleMessage {
+    async fn pick(
+        &mut self,
+        ctx: &Context,
+        config: PagedSelectorConfig<'_, PickerItem>,
+        items: Vec<(PickerItem, String)>,
+        emoji: &str,
+    ) -> Result<Vec<PickerItem>, PagedSelectorError> {
+        let values = items.iter().map(|(i, _)| *i).collect::<Vec<_>>();
+        let picked = self
+            .paged_selector(ctx, config, &values, labels(&items, emoji))
+            .await?;
+        Ok(picked.into_iter().copied().collect())
+    }
+
+    /// Lets the user pick roles of the cached guild
+    pub async fn role_picker(
+        &mut self,
+        ctx: &Context,
+        config: PagedSelectorConfig<'_, PickerItem>,
+        guild_id: GuildId,
+        filter: &RolePickerFilter,
+    ) -> Result<HashSet<RoleId>, PagedSelectorError> {
+        let bot = ctx.cache.current_user_id();
+        let items = ctx
+            .cache
+            .guild_field(guild_id, |g| filter.items(g, bot))
+            .unwrap_or_default();
+        let picked = self.pick(ctx, config, items, "🏷️").await?;
+        Ok(picked
+            .into_iter()
+            .filter_map(|i| match i {
+                PickerItem::Role(id) => Some(id),
+                _ => None,
+            })
+            .collect())
+    }
+
+    /// Lets the user pick channels of the cached guild
+    pub async fn channel_picker(
+        &mut self,
+        ctx: &Context,
+        config: PagedSelectorConfig<'_, PickerItem>,
+        guild_id: GuildId,
+        filter: &ChannelPickerFilter,
+    ) -> Result<HashSet<ChannelId>, PagedSelectorError> {
+        let items = ctx
+            .cache
+            .guild_field(guild_id, |g| filter.items(g))
+            .unwrap_or_default();
+        let picked = self.pick(ctx, config, items, "💬").await?;
+        Ok(picked
+            .into_iter()
+            .filter_map(|i| match i {
+                PickerItem::Channel(id) => Some(id),
+                _ => None,
+            })
+            .collect())
+    }
+
+    /// Lets the user pick members of the cached guild. Only cached members
+    /// are listed, which needs the guild members intent.
+    pub async fn member_picker(
+        &mut self,
+        ctx: &Context,
+        config: PagedSelectorConfig<'_, PickerItem>,
+        guild_id: GuildId,
+        filter: &MemberPickerFilter,
+    ) -> Result<HashSet<UserId>, PagedSelectorError> {
+        let items = ctx
+            .cache
+            .guild_field(guild_id, |g| filter.items(g))
+            .unwrap_or_default();
+        let picked = self.pick(ctx, config, items, "👤").await?;
+        Ok(picked
+            .into_iter()
+            .filter_map(|i| match i {
+                PickerItem::User(id) => Some(id),
+                _ => None,
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        collectors::{PagedSelectorState, SelectorEvent, SelectorState},
+        components::Button,
+    };
+    use serenity::json::{json, Value};
+
+    // Role 1 is @everyone, 2 grants administrator and the bot 5 has
+    // role 3. Member 6 is an administrator, 7 has no roles and 9 owns
+    // the guild. @everyone can not see channel 12, 13 is a category.
+    fn guild() -> Guild {
+        let role = |id: u64, position: u64, permissions: u64| {
+            json!({
+                "id": id.to_string(), "name": format!("role{}", id), "color": 0, "hoist": false,
+                "managed": false, "mentionable": false, "position": position,
+                "permissions": permissions.to_string(),
+            })
+        };
+        let member = |id: u64, name: &str, bot: bool, roles: &[&str]| {
+            json!({
+                "user": {
+                    "id": id.to_string(), "username": name, "discriminator": "0001",
+                    "avatar": null, "bot": bot,
+                },
+                "roles": roles, "joined_at": "2022-01-01T00:00:00.000000+00:00",
+                "deaf": false, "mute": false,
+            })
+        };
+        let channel = |id: u64, kind: u8, position: u64, overwrites: Value| {
+            json!({
+                "id": id.to_string(), "type": kind, "name": format!("channel{}", id),
+                "position": position, "permission_overwrites": overwrites,
+            })
+        };
+        let hidden = json!([{ "id": "1", "type": 0, "allow": "0", "deny": "1024" }]);
+        serde_json::from_value(json!({
+            "id": "1", "name": "guild", "owner_id": "9", "afk_timeout": 300,
+            "default_message_notifications": 0, "explicit_content_filter": 0,
+            "features": [], "icon": null, "joined_at": "2022-01-01T00:00:00.000000+00:00",
+            "large": false, "member_count": 4, "mfa_level": 0, "premium_tier": 0,
+            "verification_level": 0, "nsfw_level": 0, "preferred_locale": "en-US",
+            "system_channel_flags": 0, "emojis": [], "stickers": [],
+            "voice_states": [], "presences": [], "threads": [],
+            "roles": [role(1, 0, 3072), role(2, 1, 8), role(3, 2, 0), role(4, 3, 0)],
+            "members": [
+                member(5, "bot", true, &["3"]),
+                member(6, "admin", false, &["2"]),
+                member(7, "guest", false, &[]),
+                member(9, "owner", false, &[]),
+            ],
+            "channels": [
+                channel(10, 0, 0, json!([])),
+                channel(11, 2, 1, json!([])),
+                channel(12, 0, 2, hidden),
+                channel(13, 4, 3, json!([])),
+            ],
+        }))
+        .unwrap()
+    }
+
+    fn ids(items: Vec<(PickerItem, String)>) -> Vec<PickerItem> {
+        items.into_iter().map(|(i, _)| i).collect()
+    }
+
+    #[test]
+    fn role_filter() {
+        let guild = guild();
+        let mut filter = RolePickerFilter::default();
+        filter.below_bot(true);
+        let items = filter.items(&guild, UserId(5));
+        assert_eq!(
+            items,
+            vec![(PickerItem::Role(RoleId(2)), "role2".to_string())]
+        );
+        filter
+            .below_bot(false)
+            .permissions(Permissions::ADMINISTRATOR);
+        assert_eq!(filter.items(&guild, UserId(5)).len(), 1);
+    }
+
+    #[test]
+    fn member_permissions_of_owner_and_admin() {
+        let guild = guild();
+        let everyone = Permissions::VIEW_CHANNEL | Permissions::SEND_MESSAGES;
+        assert_eq!(member_permissions(&guild, UserId(5)), everyone);
+        assert_eq!(member_permissions(&guild, UserId(6)), Permissions::all());
+        assert_eq!(member_permissions(&guild, UserId(9)), Permissions::all());
+        assert_eq!(member_permissions(&guild, UserId(8)), Permissions::empty());
+    }
+
+    #[test]
+    fn channel_filter() {
+        let guild = guild();
+        let channels = |ids: &[u64]| {
+            ids.iter()
+                .map(|&id| PickerItem::Channel(ChannelId(id)))
+                .collect::<Vec<_>>()
+        };
+        let mut filter = ChannelPickerFilter::default();
+        assert_eq!(ids(filter.items(&guild)), channels(&[10, 11, 12]));
+
+        filter.kinds(&[ChannelType::Text]);
+        assert_eq!(ids(filter.items(&guild)), channels(&[10, 12]));
+        filter.kinds(&[ChannelType::Category]);
+        assert!(filter.items(&guild).is_empty());
+
+        let mut filter = ChannelPickerFilter::default();
+        filter.permissions(UserId(7), Permissions::VIEW_CHANNEL);
+        assert_eq!(ids(filter.items(&guild)), channels(&[10, 11]));
+        // Overwrites do not apply to administrators
+        filter.permissions(UserId(6), Permissions::VIEW_CHANNEL);
+        assert_eq!(ids(filter.items(&guild)), channels(&[10, 11, 12]));
+    }
+
+    #[test]
+    fn member_filter() {
+        let guild = guild();
+        let users = |ids: &[u64]| {
+            ids.iter()
+                .map(|&id| PickerItem::User(UserId(id)))
+                .collect::<Vec<_>>()
+        };
+        let mut filter = MemberPickerFilter::default();
+        // Sorted by name
+        assert_eq!(ids(filter.items(&guild)), users(&[6, 7, 9]));
+
+        filter.bots(true);
+        assert_eq!(ids(filter.items(&guild)), users(&[6, 5, 7, 9]));
+
+        filter.bots(false).permissions(Permissions::ADMINISTRATOR);
+        assert_eq!(ids(filter.items(&guild)), users(&[6, 9]));
+    }
+
+    #[test]
+    fn shared_names() {
+        let mut guild = guild();
+        let mut twin = guild.members[&UserId(7)].clone();
+        twin.user.id = UserId(14);
+        guild.members.insert(UserId(14), twin);
+        let items = MemberPickerFilter::default().items(&guild);
+        let values = ids(items.clone());
+        let mut state = PagedSelectorState::new(Default::default(), &values, labels(&items, "👤"));
+
+        // Both buttons are labelled guest but have their own id
+        let row = &state.render().1[0].0["components"];
+        assert_eq!(row[1]["label"], "guest");
+        assert_eq!(row[2]["label"], "guest");
+        assert_ne!(row[1]["custom_id"], row[2]["custom_id"]);
+
+        let second = row[2]["custom_id"].as_str().unwrap();
+        state.handle(&SelectorEvent::click(second));
+        let picked = state
+            .handle(&SelectorEvent::button(Button::Confirm))
+            .unwrap()
+            .unwrap();
+        assert_eq!(picked, HashSet::from([&PickerItem::User(UserId(14))]));
+    }
+}
@@ -0,0 +1,430 @@
+use std::{
+    collections::HashMap,
+    fmt::Display,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use serenity::{
+    builder::{CreateActionRow, CreateButton, CreateEmbed},
+    client::Context,
+    futures::StreamExt,
+    model::{application::component::ButtonStyle, id::UserId},
+    Error as SerenityError,
+};
+use tokio::{
+    select,
+    time::{interval_at, sleep, Instant, MissedTickBehavior},
+};
+
+use super::{cancelled, CancellationToken, ExpiryBehaviour, UpdatAbleMessage};
+use crate::interactions::MessageComponentInteractionExt;
+
+const OPTION_ID: &str = "_tools_poll_";
+const SELECT_ID: &str = "_tools_poll_select";
+const BAR_WIDTH: usize = 10;
+
+/// How the options of a poll are shown
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PollInput {
+    /// One button per option, at most 25
+    Buttons,
+    /// A select menu, at most 25 options
+    SelectMenu,
+}
+
+#[derive(Clone, Debug)]
+pub struct PollConfig {
+    base_embed: CreateEmbed,
+    input: PollInput,
+    // votes per user
+    multiple: bool,
+    changeable: bool,
+    // how long the poll is open
+    duration: Duration,
+    // minimum time between two updates of the tallies
+    refresh: Duration,
+    expiry: ExpiryBehaviour,
+    cancel_token: Option<CancellationToken>,
+}
+
+impl Default for PollConfig {
+    fn default() -> Self {
+        Self {
+            base_embed: Default::default(),
+            input: PollInput::Buttons,
+            multiple: false,
+            changeable: true,
+            duration: Duration::from_secs(300),
+            refresh: Duration::from_secs(2),
+            expiry: ExpiryBehaviour::DisableComponents,
+            cancel_token: None,
+        }
+    }
+}
+
+impl PollConfig {
+    pub fn base_embed(&mut self, base_embed: CreateEmbed) -> &mut Self {
+        self.base_embed = base_embed;
+        self
+    }
+
+    pub fn input(&mut self, input: PollInput) -> &mut Self {
+        self.input = input;
+        self
+    }
+
+    /// Allows voting for more than one option
+    pub fn multiple(&mut self, multiple: bool) -> &mut Self {
+        self.multiple = multiple;
+        self
+    }
+
+    /// Allows changing the vote. Otherwise votes can be added in multiple
+    /// choice polls but never taken back.
+    pub fn changeable(&mut self, changeable: bool) -> &mut Self {
+        self.changeable = changeable;
+        self
+    }
+
+    /// How long the poll accepts votes
+    pub fn duration(&mut self, duration: Duration) -> &mut Self {
+        self.duration = duration;
+        self
+    }
+
+    /// Tallies get updated at most once per interval
+    pub fn refresh(&mut self, refresh: Duration) -> &mut Self {
+        self.refresh = refresh;
+        self
+    }
+
+    /// What happens to the components once the poll closed. The final
+    /// tallies are always shown, the components get disabled by default.
+    pub fn expiry(&mut self, expiry: ExpiryBehaviour) -> &mut Self {
+        self.expiry = expiry;
+        self
+    }
+
+    /// Closes the poll early once the token gets cancelled
+    pub fn cancel_token(&mut self, token: CancellationToken) -> &mut Self {
+        self.cancel_token = Some(token);
+        self
+    }
+}
+
+/// Votes of a finished poll
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PollResult {
+    options: Vec<String>,
+    // option indices per user
+    votes: HashMap<UserId, Vec<usize>>,
+}
+
+impl PollResult {
+    pub fn options(&self) -> &[String] {
+        &self.options
+    }
+
+    /// Number of votes per option
+    pub fn counts(&self) -> Vec<usize> {
+        let mut counts = vec![0; self.options.len()];
+        for &o in self.votes.values().flatten() {
+            counts[o] += 1;
+        }
+        counts
+    }
+
+    /// Users that voted for the option
+    pub fn voters(&self, option: usize) -> Vec<UserId> {
+        let mut voters = self
+            .votes
+            .iter()
+            .filter(|(_, v)| v.contains(&option))
+            .map(|(u, _)| *u)
+            .collect::<Vec<_>>();
+        voters.sort();
+        voters
+    }
+
+    /// The options each user voted for
+    pub fn votes(&self) -> &HashMap<UserId, Vec<usize>> {
+        &self.votes
+    }
+}
+
+#[derive(Debug)]
+pub enum PollError {
+    Cancelled,
+    Serenity(serenity::Error),
+}
+
+impl Display for PollError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Cancelled => write!(f, "Poll was cancelled"),
+            Self::Serenity(e) => e.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for PollError {}
+
+impl From<SerenityError> for PollError {
+    fn from(e: SerenityError) -> Self {
+        Self::Serenity(e)
+    }
+}
+
+/// Pure state of a poll, votes get applied through [`PollState::vote`].
+pub struct PollState {
+    config: PollConfig,
+    result: PollResult,
+    // unix time the poll closes
+    closes: u64,
+}
+
+impl PollState {
+    pub fn new<T: ToString>(config: PollConfig, options: &[T]) -> Self {
+        let closes = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            + config.duration;
+        Self {
+            config,
+            result: PollResult {
+                options: options.iter().take(25).map(ToString::to_string).collect(),
+                votes: HashMap::new(),
+            },
+            closes: closes.as_secs(),
+        }
+    }
+
+    /// Applies a click on the option button or a selection in the menu.
+    /// Returns why the vote was refused.
+    pub fn vote(&mut self, user: UserId, custom_id: &str, values: &[String]) -> Result<(), String> {
+        let picked: Vec<usize> = if custom_id == SELECT_ID {
+            values.iter().filter_map(|v| v.parse().ok()).collect()
+        } else if let Some(o) = custom_id.strip_prefix(OPTION_ID) {
+            o.parse().into_iter().collect()
+        } else {
+            return Err("Unknown option".to_string());
+        };
+        if picked.iter().any(|&o| o >= self.result.options.len()) {
+            return Err("Unknown option".to_string());
+        }
+
+        let previous = self.result.votes.get(&user);
+        let votes = match (self.config.input, self.config.multiple, previous) {
+            // Buttons toggle the option in multiple choice polls
+            (PollInput::Buttons, true, Some(previous)) => {
+                let mut votes = previous.clone();
+                for o in picked {
+                    match votes.iter().position(|&v| v == o) {
+                        Some(i) => {
+                            votes.remove(i);
+                        }
+                        None => votes.push(o),
+                    }
+                }
+                votes
+            }
+            (_, false, _) => picked.into_iter().take(1).collect(),
+            _ => picked,
+        };
+        let removed = previous.is_some_and(|p| p.iter().any(|o| !votes.contains(o)));
+        if removed && !self.config.changeable {
+            return Err("Your vote is final".to_string());
+        }
+
+        if votes.is_empty() {
+            self.result.votes.remove(&user);
+        } else {
+            self.result.votes.insert(user, votes);
+        }
+        Ok(())
+    }
+
+    pub fn result(&self) -> &PollResult {
+        &self.result
+    }
+
+    /// Closing only changes the status line, the components are handled
+    /// by the expiry behaviour
+    pub fn render(&self, closed: bool) -> (Vec<CreateEmbed>, Vec<CreateActionRow>) {
+        let counts = self.result.counts();
+        let total = counts.iter().sum::<usize>();
+
+        let mut emb = self.config.base_embed.clone();
+        for (option, count) in self.result.options.iter().zip(&counts) {
+            let filled = (count * BAR_WIDTH).checked_div(total).unwrap_or(0);
+            let percent = (count * 100).checked_div(total).unwrap_or(0);
+            emb.field(
+                option,
+                format!(
+                    "`{}{}` {}% ({})",
+                    "█".repeat(filled),
+                    "░".repeat(BAR_WIDTH - filled),
+                    percent,
+                    count
+                ),
+                false,
+            );
+        }
+        let status = match closed {
+            true => "closed".to_string(),
+            false => format!("closes <t:{}:R>", self.closes),
+        };
+        emb.field(
+            "\u{200b}",
+            format!(
+                "{} votes from {} users, {}",
+                total,
+                self.result.votes.len(),
+                status
+            ),
+            false,
+        );
+
+        let mut rows = Vec::new();
+        match self.config.input {
+            PollInput::Buttons => {
+                for chunk in self.result.options.chunks(5).enumerate() {
+                    let mut ar = CreateActionRow::default();
+                    for (i, option) in chunk.1.iter().enumerate() {
+                        let mut b = CreateButton::default();
+                        b.custom_id(format!("{}{}", OPTION_ID, chunk.0 * 5 + i))
+                            .label(option)
+                            .style(ButtonStyle::Primary);
+                        ar.add_button(b);
+                    }
+                    rows.push(ar);
+                }
+            }
+            PollInput::SelectMenu => {
+                let mut ar = CreateActionRow::default();
+                ar.create_select_menu(|m| {
+                    m.custom_id(SELECT_ID)
+                        .min_values(1)
+                        .max_values(match self.config.multiple {
+                            true => self.result.options.len() as u64,
+                            false => 1,
+                        })
+                        .options(|o| {
+                            for (i, option) in self.result.options.iter().enumerate() {
+                                o.create_option(|op| op.label(option).value(i));
+                            }
+                            o
+                        })
+                });
+                rows.push(ar);
+            }
+        }
+        (vec![emb], rows)
+    }
+}
+
+impl UpdatAbleMessage {
+    /// Lets every user vote until the poll closes. The tallies in the
+    /// embed are updated at most once per refresh interval, refused
+    /// votes get an ephemeral reply.
+    pub async fn poll<T: ToString>(
+        &mut self,
+        ctx: &Context,
+        config: PollConfig,
+        options: &[T],
+    ) -> Result<PollResult, PollError> {
+        let refresh = config.refresh;
+        let duration = config.duration;
+        let cancel_token = config.cancel_token.clone();
+        let expiry = config.expiry.clone();
+        let mut state = PollState::new(config, options);
+
+        let (emb, ar) = state.render(false);
+        self.update(ctx, emb, ar).await?;
+
+        let mut interactions = super::component_interactions(ctx, self.msg()).await;
+        let cancelled = cancelled(cancel_token.as_ref());
+        tokio::pin!(cancelled);
+        let closes = sleep(duration);
+        tokio::pin!(closes);
+        let mut ticks = interval_at(Instant::now() + refresh, refresh);
+        // Ticks missed while nobody voted must not let the next votes
+        // through unthrottled
+        ticks.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        let mut dirty = false;
+
+        loop {
+            select! {
+                react = interactions.next() => {
+                    // Should always be some
+                    let react = react.unwrap();
+
+                    match state.vote(react.user.id, &react.data.custom_id, &react.data.values) {
+                        Ok(()) => {
+                            react.defer(ctx).await?;
+                            dirty = true;
+                        }
+                        Err(e) => react.create_quick_error(ctx, e, true).await?,
+                    }
+                },
+                _ = ticks.tick(), if dirty => {
+                    let (emb, ar) = state.render(false);
+                    self.update(ctx, emb, ar).await?;
+                    dirty = false;
+                },
+                _ = &mut closes => break,
+                _ = &mut cancelled => {
+                    self.cancel(ctx).await?;
+                    return Err(PollError::Cancelled);
+                },
+            }
+        }
+
+        let (emb, ar) = state.render(true);
+        match expiry {
+            // The final tallies are shown regardless
+            ExpiryBehaviour::Keep => self.update(ctx, emb, ar).await?,
+            expiry => self.expire(ctx, &expiry, emb, ar).await?,
+        }
+        Ok(state.result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn votes() {
+        let (a, b) = (UserId(1), UserId(2));
+        let mut config = PollConfig::default();
+        config.multiple(true);
+        let mut state = PollState::new(config.clone(), &["x", "y", "z"]);
+
+        state.vote(a, "_tools_poll_0", &[]).unwrap();
+        state.vote(a, "_tools_poll_2", &[]).unwrap();
+        state.vote(b, "_tools_poll_2", &[]).unwrap();
+        // Clicking again removes the vote
+        state.vote(a, "_tools_poll_0", &[]).unwrap();
+        assert!(state.vote(a, "_tools_poll_3", &[]).is_err());
+        assert_eq!(state.result().counts(), vec![0, 0, 2]);
+        assert_eq!(state.result().voters(2), vec![a, b]);
+
+        config
+            .multiple(false)
+            .changeable(false)
+            .input(PollInput::SelectMenu);
+        let mut state = PollState::new(config.clone(), &["x", "y"]);
+        state.vote(a, SELECT_ID, &["1".to_string()]).unwrap();
+        assert!(state.vote(a, SELECT_ID, &["0".to_string()]).is_err());
+        assert_eq!(state.result().counts(), vec![0, 1]);
+
+        // Final votes can still be added to in multiple choice polls
+        config.multiple(true).input(PollInput::Buttons);
+        let mut state = PollState::new(config, &["x", "y"]);
+        state.vote(a, "_tools_poll_0", &[]).unwrap();
+        state.vote(a, "_tools_poll_1", &[]).unwrap();
+        assert!(state.vote(a, "_tools_poll_0", &[]).is_err());
+        assert_eq!(state.result().counts(), vec![1, 1]);
+    }
+}
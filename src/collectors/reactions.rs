@@ -0,0 +1,189 @@
+use std::{sync::Arc, time::Duration};
+
+use serenity::{
+    builder::CreateEmbed,
+    client::Context,
+    collector::ReactionAction,
+    futures::{future::BoxFuture, stream::BoxStream, FutureExt, StreamExt},
+    model::id::UserId,
+    Result as SerenityResult,
+};
+use tokio::{select, time::sleep};
+
+use super::{cancelled, CancellationToken, PagedSelectorError, UpdatAbleMessage};
+use crate::components::Button;
+
+/// Config of the reaction based dialogs, for messages where buttons are
+/// awkward
+#[derive(Clone, Debug)]
+pub struct ReactionConfig {
+    // Resets after every reaction
+    timeout: Duration,
+    cancel_token: Option<CancellationToken>,
+}
+
+impl Default for ReactionConfig {
+    fn default() -> Self {
+        Self {
+            timeout: Duration::from_secs(120),
+            cancel_token: None,
+        }
+    }
+}
+
+impl ReactionConfig {
+    /// How long to wait for the next reaction
+    pub fn timeout(&mut self, timeout: Duration) -> &mut Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Stops waiting once the token gets cancelled
+    pub fn cancel_token(&mut self, token: CancellationToken) -> &mut Self {
+        self.cancel_token = Some(token);
+        self
+    }
+}
+
+// Reactions of the user that match one of the buttons
+struct ReactionButtons<'a> {
+    buttons: &'a [Button],
+    reactions: BoxStream<'static, Arc<ReactionAction>>,
+    cancelled: BoxFuture<'a, ()>,
+    timeout: Duration,
+}
+
+impl<'a> ReactionButtons<'a> {
+    // Adding and removing the reaction both count, so the user can click
+    // again without the bot removing the reaction
+    async fn next(&mut self) -> Result<Option<&'a Button>, PagedSelectorError> {
+        let idle = sleep(self.timeout);
+        tokio::pin!(idle);
+        loop {
+            select! {
+                reaction = self.reactions.next() => {
+                    let emoji = match reaction {
+                        Some(reaction) => reaction.as_inner_ref().emoji.clone(),
+                        None => return Ok(None),
+                    };
+                    if let Some(button) = self.buttons.iter().find(|b| b.emoji() == emoji) {
+                        return Ok(Some(button));
+                    }
+                },
+                _ = &mut idle => return Ok(None),
+                _ = &mut self.cancelled => return Err(PagedSelectorError::Cancelled),
+            }
+        }
+    }
+}
+
+impl UpdatAbleMessage {
+    // Shows the embed without components and reacts with the buttons
+    async fn start_reactions<'a>(
+        &mut self,
+        ctx: &Context,
+        user: UserId,
+        embed: CreateEmbed,
+        buttons: &'a [Button],
+        config: &'a ReactionConfig,
+    ) -> SerenityResult<ReactionButtons<'a>> {
+        self.update(ctx, vec![embed], Vec::new()).await?;
+        let reactions = super::message_reactions(ctx, self.msg(), user).await;
+        for button in buttons {
+            self.msg().react(ctx, button.emoji()).await?;
+        }
+        Ok(ReactionButtons {
+            buttons,
+            reactions,
+            cancelled: cancelled(config.cancel_token.as_ref()).boxed(),
+            timeout: config.timeout,
+        })
+    }
+
+    async fn remove_reactions(&self, ctx: &Context, buttons: &[Button]) -> SerenityResult<()> {
+        let msg = self.msg();
+        if msg.delete_reactions(ctx).await.is_err() {
+            // Without the permission only the own reactions can be removed
+            for button in buttons {
+                msg.channel_id
+                    .delete_reaction(ctx, msg.id, None, button.emoji())
+                    .await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Asks the user to react with ✅ or ❌. Returns whether the user
+    /// confirmed, the reactions are removed afterwards. Works on messages
+    /// that can not show buttons.
+    pub async fn reaction_confirm(
+        &mut self,
+        ctx: &Context,
+        user: UserId,
+        embed: CreateEmbed,
+        config: &ReactionConfig,
+    ) -> Result<bool, PagedSelectorError> {
+        let buttons = [Button::Confirm, Button::Abort];
+        let mut reactions = self
+            .start_reactions(ctx, user, embed, &buttons, config)
+            .await?;
+        let res = reactions.next().await;
+        drop(reactions);
+
+        if let Err(PagedSelectorError::Cancelled) = res {
+            self.cancel(ctx).await?;
+        }
+        self.remove_reactions(ctx, &buttons).await?;
+        match res? {
+            Some(Button::Confirm) => Ok(true),
+            Some(_) => Ok(false),
+            None => Err(PagedSelectorError::TimedOut),
+        }
+    }
+
+    /// Shows the pages one at a time, the user turns them with ⏪ and ⏩
+    /// and closes them with ❌. Ends without error once the user stopped
+    /// turning pages for the timeout, the reactions are removed then.
+    pub async fn reaction_paginator(
+        &mut self,
+        ctx: &Context,
+        user: UserId,
+        pages: &[CreateEmbed],
+        config: &ReactionConfig,
+    ) -> Result<(), PagedSelectorError> {
+        let page = |i: usize| {
+            let mut emb = pages.get(i).cloned().unwrap_or_default();
+            if pages.len() > 1 {
+                emb.footer(|f| f.text(format!("Page {}/{}", i + 1, pages.len())));
+            }
+            emb
+        };
+        let buttons = match pages.len() {
+            0 | 1 => vec![Button::Abort],
+            _ => vec![Button::Previous, Button::Next, Button::Abort],
+        };
+
+        let mut curr = 0;
+        let mut reactions = self
+            .start_reactions(ctx, user, page(curr), &buttons, config)
+            .await?;
+        let res = loop {
+            match reactions.next().await {
+                Ok(Some(Button::Previous)) => {
+                    curr = curr.checked_sub(1).unwrap_or(pages.len() - 1);
+                }
+                Ok(Some(Button::Next)) => curr = (curr + 1) % pages.len(),
+                Ok(Some(_) | None) => break Ok(()),
+                Err(e) => break Err(e),
+            }
+            self.update(ctx, vec![page(curr)], Vec::new()).await?;
+        };
+        drop(reactions);
+
+        if let Err(PagedSelectorError::Cancelled) = res {
+            self.cancel(ctx).await?;
+        }
+        self.remove_reactions(ctx, &buttons).await?;
+        res
+    }
+}
@@ -0,0 +1,418 @@
+use std::{fmt::Display, sync::Arc, time::Duration};
+
+use serenity::{
+    async_trait,
+    builder::CreateEmbed,
+    client::Context,
+    futures::{future::BoxFuture, stream::BoxStream, FutureExt, StreamExt},
+    model::{
+        channel::{Attachment, Message},
+        id::{ChannelId, UserId},
+    },
+    Error as SerenityError,
+};
+use tokio::{select, time::sleep};
+
+use super::{cancelled, CancellationToken, ValueParser};
+use crate::builder::CreateEmbedExt;
+
+#[derive(Clone, Debug)]
+pub struct ReplyPromptConfig {
+    // How long to wait for each reply
+    timeout: Duration,
+    // How often an invalid reply gets another chance
+    retries: usize,
+    delete_reply: bool,
+    cancel_token: Option<CancellationToken>,
+}
+
+impl Default for ReplyPromptConfig {
+    fn default() -> Self {
+        Self {
+            timeout: Duration::from_secs(120),
+            retries: 2,
+            delete_reply: false,
+            cancel_token: None,
+        }
+    }
+}
+
+impl ReplyPromptConfig {
+    /// How long to wait for each reply
+    pub fn timeout(&mut self, timeout: Duration) -> &mut Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// How often the user is asked again after an invalid reply
+    pub fn retries(&mut self, retries: usize) -> &mut Self {
+        self.retries = retries;
+        self
+    }
+
+    /// Deletes the replies of the user once they were read
+    pub fn delete_reply(&mut self, delete: bool) -> &mut Self {
+        self.delete_reply = delete;
+        self
+    }
+
+    /// Stops waiting once the token gets cancelled
+    pub fn cancel_token(&mut self, token: CancellationToken) -> &mut Self {
+        self.cancel_token = Some(token);
+        self
+    }
+}
+
+#[derive(Debug)]
+pub enum ReplyError {
+    TimedOut,
+    /// The cancel token was cancelled
+    Cancelled,
+    /// The last reply was invalid and no retries were left
+    Invalid(String),
+    Serenity(SerenityError),
+}
+
+impl Display for ReplyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::TimedOut => write!(f, "No reply in time"),
+            Self::Cancelled => write!(f, "Prompt was cancelled"),
+            Self::Invalid(e) => write!(f, "Reply invalid: {}", e),
+            Self::Serenity(e) => e.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for ReplyError {}
+
+impl From<SerenityError> for ReplyError {
+    fn from(e: SerenityError) -> Self {
+        Self::Serenity(e)
+    }
+}
+
+/// Which files a reply has to contain
+#[derive(Clone, Debug)]
+pub struct AttachmentFilter {
+    min_count: usize,
+    max_count: usize,
+    // in bytes
+    max_size: Option<u64>,
+    // Accepted if the content type starts with one of them or the
+    // extension is one of the others, all are accepted if both are empty
+    content_types: Vec<String>,
+    extensions: Vec<String>,
+    download: bool,
+}
+
+impl Default for AttachmentFilter {
+    fn default() -> Self {
+        Self {
+            min_count: 1,
+            max_count: 1,
+            max_size: None,
+            content_types: Vec::new(),
+            extensions: Vec::new(),
+            download: false,
+        }
+    }
+}
+
+impl AttachmentFilter {
+    /// How many files the reply has to contain, one by default
+    pub fn count(&mut self, min: usize, max: usize) -> &mut Self {
+        self.min_count = min;
+        self.max_count = max;
+        self
+    }
+
+    /// Maximum size of each file in bytes
+    pub fn max_size(&mut self, bytes: u64) -> &mut Self {
+        self.max_size = Some(bytes);
+        self
+    }
+
+    /// Accepts files whose content type starts with this, e.g. `image/`
+    pub fn content_type<T: ToString>(&mut self, content_type: T) -> &mut Self {
+        self.content_types.push(content_type.to_string());
+        self
+    }
+
+    /// Accepts files with this extension, e.g. `csv`
+    pub fn extension<T: ToString>(&mut self, extension: T) -> &mut Self {
+        let extension = extension.to_string();
+        self.extensions
+            .push(extension.trim_start_matches('.').to_lowercase());
+        self
+    }
+
+    /// Downloads the files into memory
+    pub fn download(&mut self, download: bool) -> &mut Self {
+        self.download = download;
+        self
+    }
+
+    fn accepts(&self, attachment: &Attachment) -> bool {
+        if self.content_types.is_empty() && self.extensions.is_empty() {
+            return true;
+        }
+        let content_type = attachment.content_type.as_deref().unwrap_or_default();
+        let extension = attachment
+            .filename
+            .rsplit_once('.')
+            .map(|(_, e)| e.to_lowercase());
+        self.content_types
+            .iter()
+            .any(|t| content_type.starts_with(t.as_str()))
+            || extension.is_some_and(|e| self.extensions.contains(&e))
+    }
+
+    fn check(&self, attachments: &[Attachment]) -> Result<(), String> {
+        let count = attachments.len();
+        if count < self.min_count || count > self.max_count {
+            return Err(match (self.min_count, self.max_count) {
+                (1, 1) => "Expected one file".to_string(),
+                (min, max) if min == max => format!("Expected {} files", min),
+                (min, max) => format!("Expected {} to {} files", min, max),
+            });
+        }
+        for attachment in attachments {
+            if self.max_size.is_some_and(|max| attachment.size > max) {
+                return Err(format!(
+                    "{} is larger than {} KB",
+                    attachment.filename,
+                    self.max_size.unwrap_or_default() / 1024
+                ));
+            }
+            if !self.accepts(attachment) {
+                let accepted = self
+                    .content_types
+                    .iter()
+                    .cloned()
+                    .chain(self.extensions.iter().map(|e| format!(".{}", e)))
+                    .collect::<Vec<_>>();
+                return Err(format!(
+                    "{} is not one of {}",
+                    attachment.filename,
+                    accepted.join(", ")
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A file of the reply, with its bytes if the filter downloads them
+#[derive(Clone, Debug)]
+pub struct ReceivedAttachment {
+    pub attachment: Attachment,
+    pub data: Option<Vec<u8>>,
+}
+
+#[async_trait]
+pub trait ReplyCollectorExt {
+    /// Sends the prompt and waits for the next message of the user in
+    /// the channel
+    async fn await_reply<P: ToString + Send>(
+        &self,
+        ctx: &Context,
+        user: UserId,
+        prompt: P,
+        config: &ReplyPromptConfig,
+    ) -> Result<Message, ReplyError>;
+
+    /// Like [`ReplyCollectorExt::await_reply`], but the reply gets parsed.
+    /// If parsing fails the user is shown the error and can reply again
+    /// as often as the config allows.
+    async fn await_reply_with<P, T, F>(
+        &self,
+        ctx: &Context,
+        user: UserId,
+        prompt: P,
+        config: &ReplyPromptConfig,
+        parse: F,
+    ) -> Result<T, ReplyError>
+    where
+        P: ToString + Send,
+        T: Send,
+        F: FnMut(&Message) -> Result<T, String> + Send;
+
+    /// Like [`ReplyCollectorExt::await_reply_with`] with one of the
+    /// parsers of this crate, e.g. a [`DurationParser`](super::DurationParser)
+    async fn await_value<P, V>(
+        &self,
+        ctx: &Context,
+        user: UserId,
+        prompt: P,
+        config: &ReplyPromptConfig,
+        parser: &V,
+    ) -> Result<V::Output, ReplyError>
+    where
+        P: ToString + Send,
+        V: ValueParser + Sync,
+        V::Output: Send;
+
+    /// Waits for a message of the user with files that pass the filter.
+    /// Invalid uploads are answered like invalid replies.
+    async fn await_attachments<P: ToString + Send>(
+        &self,
+        ctx: &Context,
+        user: UserId,
+        prompt: P,
+        config: &ReplyPromptConfig,
+        filter: &AttachmentFilter,
+    ) -> Result<Vec<ReceivedAttachment>, ReplyError>;
+}
+
+#[async_trait]
+impl ReplyCollectorExt for ChannelId {
+    async fn await_reply<P: ToString + Send>(
+        &self,
+        ctx: &Context,
+        user: UserId,
+        prompt: P,
+        config: &ReplyPromptConfig,
+    ) -> Result<Message, ReplyError> {
+        self.await_reply_with(ctx, user, prompt, config, |m| Ok(m.clone()))
+            .await
+    }
+
+    async fn await_reply_with<P, T, F>(
+        &self,
+        ctx: &Context,
+        user: UserId,
+        prompt: P,
+        config: &ReplyPromptConfig,
+        mut parse: F,
+    ) -> Result<T, ReplyError>
+    where
+        P: ToString + Send,
+        T: Send,
+        F: FnMut(&Message) -> Result<T, String> + Send,
+    {
+        let mut replies = Replies::start(ctx, *self, user, prompt, config).await?;
+        loop {
+            let reply = replies.next().await?;
+            let parsed = parse(&reply);
+            replies.finish(&reply).await?;
+            match parsed {
+                Ok(value) => return Ok(value),
+                Err(e) => replies.reject(e).await?,
+            }
+        }
+    }
+
+    async fn await_value<P, V>(
+        &self,
+        ctx: &Context,
+        user: UserId,
+        prompt: P,
+        config: &ReplyPromptConfig,
+        parser: &V,
+    ) -> Result<V::Output, ReplyError>
+    where
+        P: ToString + Send,
+        V: ValueParser + Sync,
+        V::Output: Send,
+    {
+        self.await_reply_with(ctx, user, prompt, config, |m| parser.parse(&m.content))
+            .await
+    }
+
+    async fn await_attachments<P: ToString + Send>(
+        &self,
+        ctx: &Context,
+        user: UserId,
+        prompt: P,
+        config: &ReplyPromptConfig,
+        filter: &AttachmentFilter,
+    ) -> Result<Vec<ReceivedAttachment>, ReplyError> {
+        let mut replies = Replies::start(ctx, *self, user, prompt, config).await?;
+        loop {
+            let reply = replies.next().await?;
+            let checked = filter.check(&reply.attachments);
+            // Deleting the message deletes the files as well
+            let mut received = Vec::new();
+            if checked.is_ok() {
+                for attachment in &reply.attachments {
+                    let data = match filter.download {
+                        true => Some(attachment.download().await?),
+                        false => None,
+                    };
+                    received.push(ReceivedAttachment {
+                        attachment: attachment.clone(),
+                        data,
+                    });
+                }
+            }
+            replies.finish(&reply).await?;
+            match checked {
+                Ok(()) => return Ok(received),
+                Err(e) => replies.reject(e).await?,
+            }
+        }
+    }
+}
+
+// The replies of the user to a prompt, within the limits of the config
+struct Replies<'a> {
+    ctx: &'a Context,
+    channel: ChannelId,
+    config: &'a ReplyPromptConfig,
+    replies: BoxStream<'static, Arc<Message>>,
+    cancelled: BoxFuture<'a, ()>,
+    retries: usize,
+}
+
+impl<'a> Replies<'a> {
+    async fn start<P: ToString>(
+        ctx: &'a Context,
+        channel: ChannelId,
+        user: UserId,
+        prompt: P,
+        config: &'a ReplyPromptConfig,
+    ) -> Result<Replies<'a>, ReplyError> {
+        // Listen first, the user might be quick
+        let replies = super::channel_messages(ctx, channel, user).await;
+        channel
+            .send_message(ctx, |m| m.set_embed(CreateEmbed::info_box(prompt)))
+            .await?;
+        Ok(Self {
+            ctx,
+            channel,
+            config,
+            replies,
+            cancelled: cancelled(config.cancel_token.as_ref()).boxed(),
+            retries: config.retries,
+        })
+    }
+
+    async fn next(&mut self) -> Result<Arc<Message>, ReplyError> {
+        select! {
+            reply = self.replies.next() => reply.ok_or(ReplyError::TimedOut),
+            _ = sleep(self.config.timeout) => Err(ReplyError::TimedOut),
+            _ = &mut self.cancelled => Err(ReplyError::Cancelled),
+        }
+    }
+
+    // Deletes the reply if configured
+    async fn finish(&mut self, reply: &Message) -> Result<(), ReplyError> {
+        if self.config.delete_reply {
+            reply.delete(self.ctx).await?;
+        }
+        Ok(())
+    }
+
+    // Shows the error so the user can try again, if retries are left
+    async fn reject(&mut self, error: String) -> Result<(), ReplyError> {
+        if self.retries == 0 {
+            return Err(ReplyError::Invalid(error));
+        }
+        self.retries -= 1;
+        self.channel
+            .send_message(self.ctx, |m| m.set_embed(CreateEmbed::error_box(error)))
+            .await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,1297 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Display,
+    hash::Hash,
+    ops::Range,
+};
+
+use serenity::{
+    builder::{CreateActionRow, CreateButton, CreateEmbed},
+    json::{json, Value},
+    model::{
+        application::{
+            component::ButtonStyle, interaction::message_component::MessageComponentInteraction,
+        },
+        channel::ReactionType,
+        id::UserId,
+    },
+};
+
+use super::{PagedSelectorConfig, PagedSelectorError, SelectorControl};
+use crate::{builder::CreateActionRowExt, components::Button};
+
+const ITEM_ID: &str = "_tools_selector_";
+const GROUP_MENU_ID: &str = "_tools_selector_group";
+const QUANTITY_LABEL_ID: &str = "_tools_quantity_label";
+// Discord allows 25 fields with 1024 characters each and 6000 characters
+// in total per embed, the rest is left to the base embed
+const PAGE_FIELDS: usize = 10;
+const PAGE_CHARS: usize = 4000;
+const FIELD_VALUE_LEN: usize = 1024;
+
+/// Input for a selector state, usually a clicked component
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SelectorEvent {
+    pub custom_id: String,
+    /// Chosen values if the event came from a select menu
+    pub values: Vec<String>,
+    /// Who caused the event
+    pub user_id: UserId,
+}
+
+impl SelectorEvent {
+    pub fn click<D: ToString>(custom_id: D) -> Self {
+        Self {
+            custom_id: custom_id.to_string(),
+            values: Vec::new(),
+            user_id: UserId::default(),
+        }
+    }
+
+    pub fn by(mut self, user_id: UserId) -> Self {
+        self.user_id = user_id;
+        self
+    }
+
+    pub fn button(button: Button) -> Self {
+        Self::click(button.id())
+    }
+}
+
+impl From<&MessageComponentInteraction> for SelectorEvent {
+    fn from(mci: &MessageComponentInteraction) -> Self {
+        Self {
+            custom_id: mci.data.custom_id.clone(),
+            values: mci.data.values.clone(),
+            user_id: mci.user.id,
+        }
+    }
+}
+
+/// The logic of a selector without any Discord I/O.
+/// `UpdatAbleMessage::run_selector` drives it with component interactions.
+pub trait SelectorState {
+    type Output;
+
+    /// Embeds and action rows for the current state
+    fn render(&self) -> (Vec<CreateEmbed>, Vec<CreateActionRow>);
+
+    /// Applies the event. Returns the result once the selector is done,
+    /// otherwise the state needs to be rendered again.
+    fn handle(&mut self, event: &SelectorEvent)
+        -> Option<Result<Self::Output, PagedSelectorError>>;
+
+    /// Progress to store so the selector can be resumed after a restart
+    fn save(&self) -> Value {
+        Value::Null
+    }
+
+    /// Continues from saved progress, invalid parts are ignored
+    fn restore(&mut self, _saved: &Value) {}
+}
+
+// Allows to keep the state after the selector stopped
+impl<S: SelectorState> SelectorState for &mut S {
+    type Output = S::Output;
+
+    fn render(&self) -> (Vec<CreateEmbed>, Vec<CreateActionRow>) {
+        (**self).render()
+    }
+
+    fn handle(
+        &mut self,
+        event: &SelectorEvent,
+    ) -> Option<Result<Self::Output, PagedSelectorError>> {
+        (**self).handle(event)
+    }
+
+    fn save(&self) -> Value {
+        (**self).save()
+    }
+
+    fn restore(&mut self, saved: &Value) {
+        (**self).restore(saved)
+    }
+}
+
+// Indices below `len` in the saved array
+pub(crate) fn saved_indices(saved: &Value, key: &str, len: usize) -> Vec<usize> {
+    saved[key]
+        .as_array()
+        .map(|a| {
+            a.iter()
+                .filter_map(|i| i.as_u64().map(|i| i as usize))
+                .filter(|i| *i < len)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Result of the grouped paged selector
+#[derive(Debug)]
+pub struct GroupedSelection<'a, T> {
+    groups: Vec<(&'a str, HashSet<&'a T>)>,
+}
+
+impl<'a, T: Eq + Hash> GroupedSelection<'a, T> {
+    /// Selected values for each group, in the order the groups were passed in
+    pub fn groups(&self) -> &[(&'a str, HashSet<&'a T>)] {
+        &self.groups
+    }
+
+    pub fn into_groups(self) -> Vec<(&'a str, HashSet<&'a T>)> {
+        self.groups
+    }
+
+    /// All selected values regardless of their group
+    pub fn flatten(self) -> HashSet<&'a T> {
+        self.groups.into_iter().flat_map(|(_, s)| s).collect()
+    }
+}
+
+// Paging shared by all selectors. Items are addressed by their index
+// in `values`, pages never span multiple groups.
+struct Paging<'a, T> {
+    config: PagedSelectorConfig<'a, T>,
+    values: Vec<&'a T>,
+    buttons: Vec<(ReactionType, String)>,
+    ids: Vec<String>,
+    mapping: HashMap<String, usize>,
+    groups: Vec<(Option<&'a str>, Range<usize>)>,
+    pages: Vec<Range<usize>>,
+    // group of each page
+    page_groups: Vec<usize>,
+    // first page of each group
+    group_pages: Vec<Option<usize>>,
+    curr_page: usize,
+    max: usize,
+}
+
+impl<'a, T: Display + Eq + Hash> Paging<'a, T> {
+    fn restore_page(&mut self, saved: &Value) {
+        if let Some(page) = saved["page"].as_u64() {
+            if (page as usize) < self.pages.len() {
+                self.curr_page = page as usize;
+            }
+        }
+    }
+
+    // `reserved` rows are used by other components than items and controls
+    fn new<F>(
+        config: PagedSelectorConfig<'a, T>,
+        groups: Vec<(Option<&'a str>, Vec<&'a T>)>,
+        reserved: usize,
+        button: F,
+    ) -> Self
+    where
+        F: Fn(&T) -> (ReactionType, String),
+    {
+        let rows_pages = paged_selector_rows(&config, reserved, |rows| {
+            groups.len() > 1
+                || groups
+                    .iter()
+                    .any(|(_, vs)| vs.len() > config.items_rows * rows)
+        });
+        let per_page = config.items_rows * rows_pages;
+        let max = config.max.unwrap_or(usize::MAX);
+
+        let mut values = Vec::new();
+        let mut ids = Vec::new();
+        let mut buttons = Vec::new();
+        let mut group_ranges = Vec::with_capacity(groups.len());
+        let mut pages = Vec::new();
+        let mut page_groups = Vec::new();
+        let mut group_pages = Vec::with_capacity(groups.len());
+        for (g, (name, vs)) in groups.into_iter().enumerate() {
+            let offset = values.len();
+            group_pages.push(if vs.is_empty() {
+                None
+            } else {
+                Some(pages.len())
+            });
+            for start in (0..vs.len()).step_by(per_page) {
+                pages.push(offset + start..offset + vs.len().min(start + per_page));
+                page_groups.push(g);
+            }
+            // labels can repeat, the index is unique
+            for v in vs {
+                ids.push(format!("{}{}", ITEM_ID, values.len()));
+                buttons.push(button(v));
+                values.push(v);
+            }
+            group_ranges.push((name, offset..values.len()));
+        }
+        // there is always something to render
+        if pages.is_empty() {
+            pages.push(0..0);
+            page_groups.push(0);
+        }
+
+        let mapping = ids
+            .iter()
+            .enumerate()
+            .map(|(i, id)| (id.clone(), i))
+            .collect();
+
+        Self {
+            config,
+            values,
+            buttons,
+            ids,
+            mapping,
+            groups: group_ranges,
+            pages,
+            page_groups,
+            group_pages,
+            curr_page: 0,
+            max,
+        }
+    }
+
+    fn page(&self) -> Range<usize> {
+        self.pages[self.curr_page].clone()
+    }
+
+    fn pre_selected(&self) -> Vec<usize> {
+        let mut pre = Vec::new();
+        if let Some(pre_sel) = self.config.pre_selected {
+            for (i, v) in self.values.iter().enumerate() {
+                if pre_sel.contains(v) {
+                    pre.push(i);
+                }
+            }
+        }
+        pre
+    }
+
+    fn in_bounds(&self, count: usize) -> bool {
+        count >= self.config.min && count <= self.max
+    }
+
+    // Moves to the next or previous page. Returns false for other buttons.
+    fn navigate(&mut self, button: &Button) -> bool {
+        match button {
+            Button::Next => {
+                if self.curr_page + 1 < self.pages.len() {
+                    self.curr_page += 1;
+                }
+                true
+            }
+            Button::Previous => {
+                self.curr_page = self.curr_page.saturating_sub(1);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn toggle(&self, item: usize, selected: &mut HashSet<usize>) {
+        if !selected.remove(&item) && selected.len() < self.max {
+            selected.insert(item);
+        }
+    }
+
+    // Applies one of the bulk controls without going above max
+    fn apply(&self, button: &Button, selected: &mut HashSet<usize>) {
+        match button {
+            Button::SelectPage | Button::SelectAll => {
+                let items = if let Button::SelectPage = button {
+                    self.page()
+                } else {
+                    0..self.values.len()
+                };
+                for i in items {
+                    if selected.len() >= self.max {
+                        break;
+                    }
+                    selected.insert(i);
+                }
+            }
+            Button::ClearAll => selected.clear(),
+            Button::Invert => {
+                let inverted: HashSet<usize> = (0..self.values.len())
+                    .filter(|i| !selected.contains(i))
+                    .collect();
+                if inverted.len() <= self.max {
+                    *selected = inverted;
+                }
+            }
+            _ => (),
+        }
+    }
+
+    // `mark` renders what is shown in front of each item. Long lists
+    // only show the pages around the current one, so the embed stays
+    // within Discord's limits.
+    fn embed<F: Fn(usize) -> String>(&self, mark: F) -> CreateEmbed {
+        let mut emb = self.config.base_embed.clone();
+        let group = self.page_groups[self.curr_page];
+        let name = self.groups.get(group).and_then(|(name, _)| *name);
+        let group_pages = (0..self.pages.len())
+            .filter(|i| self.page_groups[*i] == group)
+            .collect::<Vec<_>>();
+        let fields = group_pages
+            .iter()
+            .enumerate()
+            .map(|(n, i)| {
+                let title = match name {
+                    Some(name) => format!("{} - Page {}", name, n + 1),
+                    None => format!("Page {}", n + 1),
+                };
+                let current = if *i == self.curr_page {
+                    " (current)"
+                } else {
+                    ""
+                };
+                let items = self.pages[*i]
+                    .clone()
+                    .map(|i| format!("{} | {}", mark(i), self.values[i]))
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                (
+                    format!("{}{}", title, current),
+                    items.chars().take(FIELD_VALUE_LEN).collect::<String>(),
+                )
+            })
+            .collect::<Vec<_>>();
+
+        // Grows the shown pages from the current one to both sides
+        let curr = group_pages
+            .iter()
+            .position(|i| *i == self.curr_page)
+            .unwrap_or_default();
+        let len = |f: &(String, String)| f.0.chars().count() + f.1.chars().count();
+        let (mut start, mut end) = (curr, curr + 1);
+        let mut chars = len(&fields[curr]);
+        let fits = |chars: usize, start: usize, end: usize, field: &(String, String)| {
+            end - start < PAGE_FIELDS && chars + len(field) <= PAGE_CHARS
+        };
+        loop {
+            let mut grown = false;
+            if start > 0 && fits(chars, start, end, &fields[start - 1]) {
+                start -= 1;
+                chars += len(&fields[start]);
+                grown = true;
+            }
+            if end < fields.len() && fits(chars, start, end, &fields[end]) {
+                chars += len(&fields[end]);
+                end += 1;
+                grown = true;
+            }
+            if !grown {
+                break;
+            }
+        }
+        for (title, items) in &fields[start..end] {
+            emb.field(title, items, true);
+        }
+        emb
+    }
+
+    // Buttons are rebuilt on every render to reflect the selection
+    fn item_rows<F: Fn(usize) -> bool>(&self, selected: F) -> Vec<CreateActionRow> {
+        let items: Vec<usize> = self.page().collect();
+        items
+            .chunks(self.config.items_rows)
+            .map(|row| {
+                let mut ar = CreateActionRow::default();
+                for i in row {
+                    let (emoji, title) = &self.buttons[*i];
+                    let (style, emoji) = if selected(*i) {
+                        (
+                            self.config.selected_style,
+                            self.config.selected_button_emoji.as_ref().unwrap_or(emoji),
+                        )
+                    } else {
+                        (
+                            self.config.unselected_style,
+                            self.config
+                                .unselected_button_emoji
+                                .as_ref()
+                                .unwrap_or(emoji),
+                        )
+                    };
+                    let mut button = CreateButton::default();
+                    button
+                        .emoji(emoji.clone())
+                        .label(title)
+                        .style(style)
+                        .custom_id(&self.ids[*i]);
+                    ar.add_button(button);
+                }
+                ar
+            })
+            .collect()
+    }
+
+    // Confirm, abort, `extra`, previous, next and the bulk controls
+    fn control_rows(
+        &self,
+        confirm: bool,
+        extra: Option<CreateButton>,
+        selected: &HashSet<usize>,
+    ) -> Vec<CreateActionRow> {
+        let mut sar = CreateActionRow::default();
+        let mut conf_button = Button::Confirm.create();
+        conf_button.disabled(!confirm);
+        sar.add_button(conf_button).abort_button();
+        let mut buttons = 2;
+        if let Some(extra) = extra {
+            sar.add_button(extra);
+            buttons += 1;
+        }
+        if self.curr_page > 0 {
+            sar.prev_button();
+            buttons += 1;
+        }
+        if self.curr_page + 1 < self.pages.len() {
+            sar.next_button();
+            buttons += 1;
+        }
+
+        let controls = &self.config.controls;
+        if controls.is_empty() {
+            return vec![sar];
+        }
+
+        let page = self.page();
+        let all = 0..self.values.len();
+        let mut car = CreateActionRow::default();
+        for control in controls {
+            let mut b = control.button().create();
+            let disabled = match control {
+                SelectorControl::SelectPage => {
+                    selected.len() >= self.max || page.clone().all(|i| selected.contains(&i))
+                }
+                SelectorControl::SelectAll => {
+                    selected.len() >= self.max || all.clone().all(|i| selected.contains(&i))
+                }
+                SelectorControl::ClearAll => selected.is_empty(),
+                SelectorControl::Invert => all.len() - selected.len() > self.max,
+            };
+            b.disabled(disabled);
+            if buttons + controls.len() <= 5 {
+                sar.add_button(b);
+            } else {
+                car.add_button(b);
+            }
+        }
+
+        if buttons + controls.len() <= 5 {
+            vec![sar]
+        } else {
+            vec![sar, car]
+        }
+    }
+}
+
+// Number of item rows on each page. `reserved` rows are used by other
+// components. Bulk controls that do not fit into the last row take away
+// one more row from the items.
+fn paged_selector_rows<T, F>(config: &PagedSelectorConfig<T>, reserved: usize, paged: F) -> usize
+where
+    F: Fn(usize) -> bool,
+{
+    let rows = config.rows_pages.min(4 - reserved);
+    let fits = |rows: usize| {
+        let nav = if paged(rows) { 2 } else { 0 };
+        2 + nav + config.controls.len() <= 5
+    };
+    if fits(rows) {
+        rows
+    } else {
+        rows.min(3 - reserved)
+    }
+}
+
+/// State of `UpdatAbleMessage::paged_selector`
+pub struct PagedSelectorState<'a, T> {
+    paging: Paging<'a, T>,
+    selected: HashSet<usize>,
+}
+
+impl<'a, T: Display + Eq + Hash> PagedSelectorState<'a, T> {
+    pub fn new<F>(config: PagedSelectorConfig<'a, T>, values: &'a [T], button: F) -> Self
+    where
+        F: Fn(&T) -> (ReactionType, String),
+    {
+        let paging = Paging::new(config, vec![(None, values.iter().collect())], 0, button);
+        let selected = paging.pre_selected().into_iter().collect();
+        Self { paging, selected }
+    }
+}
+
+impl<'a, T: Display + Eq + Hash> SelectorState for PagedSelectorState<'a, T> {
+    type Output = HashSet<&'a T>;
+
+    fn render(&self) -> (Vec<CreateEmbed>, Vec<CreateActionRow>) {
+        let p = &self.paging;
+        let emb = p.embed(|i| {
+            if self.selected.contains(&i) {
+                p.config.selected_emoji.to_string()
+            } else {
+                p.config.unselected_emoj.to_string()
+            }
+        });
+        let mut ar = p.item_rows(|i| self.selected.contains(&i));
+        ar.append(&mut p.control_rows(p.in_bounds(self.selected.len()), None, &self.selected));
+        (vec![emb], ar)
+    }
+
+    fn handle(
+        &mut self,
+        event: &SelectorEvent,
+    ) -> Option<Result<Self::Output, PagedSelectorError>> {
+        match event.custom_id.parse::<Button>() {
+            Ok(Button::Confirm) => {
+                if self.paging.in_bounds(self.selected.len()) {
+                    let values = &self.paging.values;
+                    return Some(Ok(self.selected.iter().map(|i| values[*i]).collect()));
+                }
+            }
+            Ok(Button::Abort) => return Some(Err(PagedSelectorError::Aborted)),
+            Ok(b) => {
+                if !self.paging.navigate(&b) {
+                    self.paging.apply(&b, &mut self.selected);
+                }
+            }
+            Err(_) => {
+                if let Some(i) = self.paging.mapping.get(&event.custom_id) {
+                    self.paging.toggle(*i, &mut self.selected);
+                }
+            }
+        }
+        None
+    }
+
+    fn save(&self) -> Value {
+        let mut selected = self.selected.iter().collect::<Vec<_>>();
+        selected.sort();
+        json!({ "page": self.paging.curr_page, "selected": selected })
+    }
+
+    fn restore(&mut self, saved: &Value) {
+        self.paging.restore_page(saved);
+        if saved["selected"].is_array() {
+            let len = self.paging.values.len();
+            self.selected = saved_indices(saved, "selected", len).into_iter().collect();
+        }
+    }
+}
+
+/// State of `UpdatAbleMessage::ranked_selector`
+pub struct RankedSelectorState<'a, T> {
+    paging: Paging<'a, T>,
+    // in the order things got picked in
+    ranked: Vec<usize>,
+}
+
+impl<'a, T: Display + Eq + Hash> RankedSelectorState<'a, T> {
+    pub fn new<F>(config: PagedSelectorConfig<'a, T>, values: &'a [T], button: F) -> Self
+    where
+        F: Fn(&T) -> (ReactionType, String),
+    {
+        let mut config = config;
+        // the last row holds undo instead
+        config.controls.clear();
+        let paging = Paging::new(config, vec![(None, values.iter().collect())], 0, button);
+        let mut ranked = Vec::new();
+        if let Some(pre_sel) = paging.config.pre_selected {
+            for s in pre_sel {
+                if let Some(i) = paging.values.iter().position(|v| v == s) {
+                    if !ranked.contains(&i) {
+                        ranked.push(i);
+                    }
+                }
+            }
+        }
+        Self { paging, ranked }
+    }
+}
+
+impl<'a, T: Display + Eq + Hash> SelectorState for RankedSelectorState<'a, T> {
+    type Output = Vec<&'a T>;
+
+    fn render(&self) -> (Vec<CreateEmbed>, Vec<CreateActionRow>) {
+        let p = &self.paging;
+        let emb = p.embed(|i| match self.ranked.iter().position(|r| *r == i) {
+            Some(pos) => format!("{}.", pos + 1),
+            None => p.config.unselected_emoj.to_string(),
+        });
+        let selected: HashSet<usize> = self.ranked.iter().copied().collect();
+        let mut ar = p.item_rows(|i| selected.contains(&i));
+        let mut undo_button = Button::Undo.create();
+        undo_button.disabled(self.ranked.is_empty());
+        ar.append(&mut p.control_rows(
+            p.in_bounds(self.ranked.len()),
+            Some(undo_button),
+            &selected,
+        ));
+        (vec![emb], ar)
+    }
+
+    fn handle(
+        &mut self,
+        event: &SelectorEvent,
+    ) -> Option<Result<Self::Output, PagedSelectorError>> {
+        match event.custom_id.parse::<Button>() {
+            Ok(Button::Confirm) => {
+                if self.paging.in_bounds(self.ranked.len()) {
+                    let values = &self.paging.values;
+                    return Some(Ok(self.ranked.iter().map(|i| values[*i]).collect()));
+                }
+            }
+            Ok(Button::Abort) => return Some(Err(PagedSelectorError::Aborted)),
+            Ok(Button::Undo) => {
+                self.ranked.pop();
+            }
+            Ok(b) => {
+                self.paging.navigate(&b);
+            }
+            Err(_) => {
+                if let Some(i) = self.paging.mapping.get(&event.custom_id) {
+                    match self.ranked.iter().position(|r| r == i) {
+                        Some(pos) => {
+                            self.ranked.remove(pos);
+                        }
+                        None if self.ranked.len() < self.paging.max => self.ranked.push(*i),
+                        None => (),
+                    }
+                }
+            }
+        }
+        None
+    }
+
+    fn save(&self) -> Value {
+        json!({ "page": self.paging.curr_page, "ranked": self.ranked })
+    }
+
+    fn restore(&mut self, saved: &Value) {
+        self.paging.restore_page(saved);
+        if saved["ranked"].is_array() {
+            self.ranked.clear();
+            for i in saved_indices(saved, "ranked", self.paging.values.len()) {
+                if !self.ranked.contains(&i) {
+                    self.ranked.push(i);
+                }
+            }
+        }
+    }
+}
+
+/// State of `UpdatAbleMessage::quantity_selector`
+pub struct QuantitySelectorState<'a, T> {
+    paging: Paging<'a, T>,
+    bounds: Vec<(u32, u32)>,
+    quantities: Vec<u32>,
+    // item the quantity buttons currently apply to
+    focused: Option<usize>,
+}
+
+impl<'a, T: Display + Eq + Hash> QuantitySelectorState<'a, T> {
+    pub fn new<F, B>(
+        config: PagedSelectorConfig<'a, T>,
+        values: &'a [T],
+        button: F,
+        bounds: B,
+    ) -> Self
+    where
+        F: Fn(&T) -> (ReactionType, String),
+        B: Fn(&T) -> (u32, u32),
+    {
+        let mut config = config;
+        config.controls.clear();
+        // one row for the quantity buttons
+        let paging = Paging::new(config, vec![(None, values.iter().collect())], 1, button);
+        let bounds: Vec<(u32, u32)> = values.iter().map(bounds).collect();
+        let mut quantities: Vec<u32> = bounds.iter().map(|(min, _)| *min).collect();
+        for i in paging.pre_selected() {
+            quantities[i] = quantities[i].max(1).min(bounds[i].1);
+        }
+        Self {
+            paging,
+            bounds,
+            quantities,
+            focused: None,
+        }
+    }
+
+    fn picked(&self) -> usize {
+        self.quantities.iter().filter(|q| **q > 0).count()
+    }
+}
+
+impl<'a, T: Display + Eq + Hash> SelectorState for QuantitySelectorState<'a, T> {
+    type Output = HashMap<&'a T, u32>;
+
+    fn render(&self) -> (Vec<CreateEmbed>, Vec<CreateActionRow>) {
+        let p = &self.paging;
+        let emb = p.embed(|i| match self.quantities[i] {
+            0 => p.config.unselected_emoj.to_string(),
+            q => format!("{}x", q),
+        });
+        let selected: HashSet<usize> = (0..self.quantities.len())
+            .filter(|i| self.quantities[*i] > 0)
+            .collect();
+        let mut ar = p.item_rows(|i| selected.contains(&i));
+        if let Some(i) = self.focused {
+            let (min, max) = self.bounds[i];
+            let mut qar = CreateActionRow::default();
+            let mut less = Button::Decrease.create();
+            less.disabled(self.quantities[i] <= min);
+            let mut label = CreateButton::default();
+            label
+                .style(ButtonStyle::Secondary)
+                .label(format!("{}: {}", p.buttons[i].1, self.quantities[i]))
+                .custom_id(QUANTITY_LABEL_ID)
+                .disabled(true);
+            let mut more = Button::Increase.create();
+            more.disabled(self.quantities[i] >= max);
+            qar.add_button(less).add_button(label).add_button(more);
+            ar.push(qar);
+        }
+        ar.append(&mut p.control_rows(p.in_bounds(selected.len()), None, &selected));
+        (vec![emb], ar)
+    }
+
+    fn handle(
+        &mut self,
+        event: &SelectorEvent,
+    ) -> Option<Result<Self::Output, PagedSelectorError>> {
+        match event.custom_id.parse::<Button>() {
+            Ok(Button::Confirm) => {
+                if self.paging.in_bounds(self.picked()) {
+                    let values = &self.paging.values;
+                    return Some(Ok(self
+                        .quantities
+                        .iter()
+                        .enumerate()
+                        .filter(|(_, q)| **q > 0)
+                        .map(|(i, q)| (values[i], *q))
+                        .collect()));
+                }
+            }
+            Ok(Button::Abort) => return Some(Err(PagedSelectorError::Aborted)),
+            Ok(Button::Decrease) => {
+                if let Some(i) = self.focused {
+                    if self.quantities[i] > self.bounds[i].0 {
+                        self.quantities[i] -= 1;
+                    }
+                }
+            }
+            Ok(Button::Increase) => {
+                if let Some(i) = self.focused {
+                    // a new item would go above the max selection
+                    let blocked = self.quantities[i] == 0 && self.picked() >= self.paging.max;
+                    if self.quantities[i] < self.bounds[i].1 && !blocked {
+                        self.quantities[i] += 1;
+                    }
+                }
+            }
+            Ok(b) => {
+                self.paging.navigate(&b);
+            }
+            Err(_) => {
+                if let Some(i) = self.paging.mapping.get(&event.custom_id) {
+                    self.focused = Some(*i);
+                }
+            }
+        }
+        None
+    }
+
+    fn save(&self) -> Value {
+        json!({ "page": self.paging.curr_page, "quantities": self.quantities })
+    }
+
+    fn restore(&mut self, saved: &Value) {
+        self.paging.restore_page(saved);
+        if let Some(quantities) = saved["quantities"].as_array() {
+            if quantities.len() == self.quantities.len() {
+                for (i, q) in quantities.iter().enumerate() {
+                    let (min, max) = self.bounds[i];
+                    let q = q.as_u64().unwrap_or(min as u64).min(max as u64) as u32;
+                    self.quantities[i] = q.max(min);
+                }
+            }
+        }
+    }
+}
+
+/// State of `UpdatAbleMessage::grouped_paged_selector`
+pub struct GroupedSelectorState<'a, T> {
+    paging: Paging<'a, T>,
+    selected: HashSet<usize>,
+}
+
+impl<'a, T: Display + Eq + Hash> GroupedSelectorState<'a, T> {
+    pub fn new<F>(
+        config: PagedSelectorConfig<'a, T>,
+        groups: &'a [(String, Vec<T>)],
+        button: F,
+    ) -> Self
+    where
+        F: Fn(&T) -> (ReactionType, String),
+    {
+        let groups = groups
+            .iter()
+            .map(|(name, vs)| (Some(name.as_str()), vs.iter().collect()))
+            .collect();
+        // one row for the group menu
+        let paging = Paging::new(config, groups, 1, button);
+        let selected = paging.pre_selected().into_iter().collect();
+        Self { paging, selected }
+    }
+}
+
+impl<'a, T: Display + Eq + Hash> SelectorState for GroupedSelectorState<'a, T> {
+    type Output = GroupedSelection<'a, T>;
+
+    fn render(&self) -> (Vec<CreateEmbed>, Vec<CreateActionRow>) {
+        let p = &self.paging;
+        let emb = p.embed(|i| {
+            if self.selected.contains(&i) {
+                p.config.selected_emoji.to_string()
+            } else {
+                p.config.unselected_emoj.to_string()
+            }
+        });
+        let mut ar = p.item_rows(|i| self.selected.contains(&i));
+
+        let group = p.page_groups[p.curr_page];
+        let mut mar = CreateActionRow::default();
+        mar.create_select_menu(|m| {
+            m.custom_id(GROUP_MENU_ID);
+            m.options(|o| {
+                // empty groups have no page to jump to
+                let groups = p
+                    .groups
+                    .iter()
+                    .enumerate()
+                    .filter(|(g, _)| p.group_pages[*g].is_some())
+                    .take(25);
+                for (g, (name, _)) in groups {
+                    let label = match name {
+                        Some(name) if !name.is_empty() => name.to_string(),
+                        _ => format!("Group {}", g + 1),
+                    };
+                    o.create_option(|op| op.label(label).value(g).default_selection(g == group));
+                }
+                o
+            })
+        });
+        ar.push(mar);
+
+        ar.append(&mut p.control_rows(p.in_bounds(self.selected.len()), None, &self.selected));
+        (vec![emb], ar)
+    }
+
+    fn handle(
+        &mut self,
+        event: &SelectorEvent,
+    ) -> Option<Result<Self::Output, PagedSelectorError>> {
+        if event.custom_id == GROUP_MENU_ID {
+            let page = event
+                .values
+                .first()
+                .and_then(|v| v.parse::<usize>().ok())
+                .and_then(|g| self.paging.group_pages.get(g).copied().flatten());
+            if let Some(page) = page {
+                self.paging.curr_page = page;
+            }
+            return None;
+        }
+
+        match event.custom_id.parse::<Button>() {
+            Ok(Button::Confirm) => {
+                if self.paging.in_bounds(self.selected.len()) {
+                    let p = &self.paging;
+                    let groups = p
+                        .groups
+                        .iter()
+                        .map(|(name, range)| {
+                            (
+                                name.unwrap_or_default(),
+                                range
+                                    .clone()
+                                    .filter(|i| self.selected.contains(i))
+                                    .map(|i| p.values[i])
+                                    .collect(),
+                            )
+                        })
+                        .collect();
+                    return Some(Ok(GroupedSelection { groups }));
+                }
+            }
+            Ok(Button::Abort) => return Some(Err(PagedSelectorError::Aborted)),
+            Ok(b) => {
+                if !self.paging.navigate(&b) {
+                    self.paging.apply(&b, &mut self.selected);
+                }
+            }
+            Err(_) => {
+                if let Some(i) = self.paging.mapping.get(&event.custom_id) {
+                    self.paging.toggle(*i, &mut self.selected);
+                }
+            }
+        }
+        None
+    }
+
+    fn save(&self) -> Value {
+        let mut selected = self.selected.iter().collect::<Vec<_>>();
+        selected.sort();
+        json!({ "page": self.paging.curr_page, "selected": selected })
+    }
+
+    fn restore(&mut self, saved: &Value) {
+        self.paging.restore_page(saved);
+        if saved["selected"].is_array() {
+            let len = self.paging.values.len();
+            self.selected = saved_indices(saved, "selected", len).into_iter().collect();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serenity::json::Value;
+
+    fn button(v: &u32) -> (ReactionType, String) {
+        (ReactionType::Unicode("🔵".to_string()), v.to_string())
+    }
+
+    // (custom_id, disabled) of every component in every row
+    fn components(rows: &[CreateActionRow]) -> Vec<Vec<(String, bool)>> {
+        rows.iter()
+            .map(|r| {
+                r.0["components"]
+                    .as_array()
+                    .unwrap()
+                    .iter()
+                    .map(|c| {
+                        (
+                            c["custom_id"].as_str().unwrap().to_string(),
+                            c.get("disabled").and_then(Value::as_bool).unwrap_or(false),
+                        )
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    fn last_row(state: &impl SelectorState) -> Vec<(String, bool)> {
+        components(&state.render().1).pop().unwrap()
+    }
+
+    fn has(row: &[(String, bool)], button: Button) -> bool {
+        row.iter().any(|(id, _)| id == button.id())
+    }
+
+    fn enabled(row: &[(String, bool)], button: Button) -> bool {
+        row.iter()
+            .any(|(id, disabled)| id == button.id() && !disabled)
+    }
+
+    fn click(item: usize) -> SelectorEvent {
+        SelectorEvent::click(format!("{}{}", ITEM_ID, item))
+    }
+
+    #[test]
+    fn paging() {
+        let values: Vec<u32> = (0..30).collect();
+        let mut state = PagedSelectorState::new(Default::default(), &values, button);
+
+        let rows = components(&state.render().1);
+        assert_eq!(rows.len(), 5);
+        assert_eq!(rows[0][0].0, "_tools_selector_0");
+        assert!(!has(&rows[4], Button::Previous));
+        assert!(has(&rows[4], Button::Next));
+
+        assert!(state.handle(&SelectorEvent::button(Button::Next)).is_none());
+        let rows = components(&state.render().1);
+        // 10 items left on the second page
+        assert_eq!(rows.len(), 3);
+        assert_eq!(rows[0][0].0, "_tools_selector_20");
+        assert!(has(&rows[2], Button::Previous));
+        assert!(!has(&rows[2], Button::Next));
+
+        // stays on the last page
+        state.handle(&SelectorEvent::button(Button::Next));
+        assert_eq!(components(&state.render().1)[0][0].0, "_tools_selector_20");
+    }
+
+    #[test]
+    fn min_bounds() {
+        let values: Vec<u32> = (0..5).collect();
+        let mut config = PagedSelectorConfig::default();
+        config.min_select(2);
+        let mut state = PagedSelectorState::new(config, &values, button);
+
+        assert!(!enabled(&last_row(&state), Button::Confirm));
+        assert!(state
+            .handle(&SelectorEvent::button(Button::Confirm))
+            .is_none());
+
+        state.handle(&SelectorEvent::click("_tools_selector_1"));
+        state.handle(&SelectorEvent::click("_tools_selector_3"));
+        assert!(enabled(&last_row(&state), Button::Confirm));
+
+        let selected = state
+            .handle(&SelectorEvent::button(Button::Confirm))
+            .unwrap()
+            .unwrap();
+        assert_eq!(selected, HashSet::from([&values[1], &values[3]]));
+    }
+
+    #[test]
+    fn pre_selection() {
+        let values: Vec<u32> = (0..5).collect();
+        let pre = [&values[2]];
+        let mut config = PagedSelectorConfig::default();
+        config.pre_selected(&pre);
+        let mut state = PagedSelectorState::new(config, &values, button);
+
+        let style = |state: &PagedSelectorState<u32>| {
+            state.render().1[0].0["components"][2]["style"].clone()
+        };
+        assert_eq!(style(&state), Value::from(ButtonStyle::Success as u8));
+
+        // deselect it again
+        state.handle(&SelectorEvent::click("_tools_selector_2"));
+        assert_eq!(style(&state), Value::from(ButtonStyle::Primary as u8));
+        let selected = state
+            .handle(&SelectorEvent::button(Button::Confirm))
+            .unwrap()
+            .unwrap();
+        assert!(selected.is_empty());
+    }
+
+    #[test]
+    fn abort() {
+        let values: Vec<u32> = (0..5).collect();
+        let mut state = PagedSelectorState::new(Default::default(), &values, button);
+        state.handle(&SelectorEvent::click("_tools_selector_1"));
+
+        assert!(matches!(
+            state.handle(&SelectorEvent::button(Button::Abort)),
+            Some(Err(PagedSelectorError::Aborted))
+        ));
+    }
+
+    #[test]
+    fn group_menu() {
+        // the first 10 groups are empty, one group has no name
+        let mut groups: Vec<(String, Vec<u32>)> = (0..30)
+            .map(|g| (format!("G{}", g), if g < 10 { vec![] } else { vec![g] }))
+            .collect();
+        groups[12].0.clear();
+        let state = GroupedSelectorState::new(Default::default(), &groups, button);
+
+        let rows = state.render().1;
+        let menu = rows
+            .iter()
+            .find_map(|r| {
+                r.0["components"]
+                    .as_array()
+                    .unwrap()
+                    .iter()
+                    .find(|c| c["custom_id"] == GROUP_MENU_ID)
+                    .cloned()
+            })
+            .unwrap();
+        let labels: Vec<&str> = menu["options"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|o| o["label"].as_str().unwrap())
+            .collect();
+        assert_eq!(labels.len(), 20);
+        assert_eq!(labels[0], "G10");
+        assert_eq!(labels[2], "Group 13");
+        assert_eq!(labels[19], "G29");
+    }
+
+    #[test]
+    fn bulk_controls() {
+        let values: Vec<u32> = (0..30).collect();
+        let mut config = PagedSelectorConfig::default();
+        config
+            .controls(&[
+                SelectorControl::SelectPage,
+                SelectorControl::SelectAll,
+                SelectorControl::ClearAll,
+                SelectorControl::Invert,
+            ])
+            .min_select(1)
+            .max_select(20);
+        let mut state = PagedSelectorState::new(config, &values, button);
+        let selected = |state: &PagedSelectorState<u32>| {
+            let mut s: Vec<usize> = state.selected.iter().copied().collect();
+            s.sort();
+            s
+        };
+
+        // Inverting nothing would select more than max
+        assert!(!enabled(&last_row(&state), Button::Invert));
+        state.handle(&SelectorEvent::button(Button::Invert));
+        assert!(state.selected.is_empty());
+
+        // The controls take a row, 15 items per page
+        state.handle(&SelectorEvent::button(Button::SelectPage));
+        assert_eq!(selected(&state), (0..15).collect::<Vec<_>>());
+        state.handle(&SelectorEvent::button(Button::Next));
+        state.handle(&SelectorEvent::button(Button::SelectPage));
+        assert_eq!(selected(&state), (0..20).collect::<Vec<_>>());
+        assert!(!enabled(&last_row(&state), Button::SelectPage));
+        assert!(!enabled(&last_row(&state), Button::SelectAll));
+
+        state.handle(&SelectorEvent::button(Button::Invert));
+        assert_eq!(selected(&state), (20..30).collect::<Vec<_>>());
+        state.handle(&SelectorEvent::button(Button::SelectAll));
+        assert_eq!(selected(&state), (0..10).chain(20..30).collect::<Vec<_>>());
+        state.handle(&SelectorEvent::button(Button::Invert));
+        assert_eq!(selected(&state), (10..20).collect::<Vec<_>>());
+
+        // Clearing everything goes below min
+        state.handle(&SelectorEvent::button(Button::ClearAll));
+        assert!(state.selected.is_empty());
+        assert!(!enabled(&last_row(&state), Button::ClearAll));
+        assert!(state
+            .handle(&SelectorEvent::button(Button::Confirm))
+            .is_none());
+    }
+
+    #[test]
+    fn ranked_order() {
+        let values: Vec<u32> = (0..5).collect();
+        let mut config = PagedSelectorConfig::default();
+        config.max_select(3);
+        let mut state = RankedSelectorState::new(config, &values, button);
+
+        // The fourth pick goes above max
+        for i in [3, 1, 4, 0] {
+            state.handle(&click(i));
+        }
+        assert_eq!(state.ranked, vec![3, 1, 4]);
+        assert_eq!(
+            state.render().0[0].0["fields"][0]["value"],
+            "⬛ | 0\n2. | 1\n⬛ | 2\n1. | 3\n3. | 4"
+        );
+
+        state.handle(&SelectorEvent::button(Button::Undo));
+        state.handle(&click(2));
+        // Clicking a ranked item removes it
+        state.handle(&click(3));
+        let ranked = state
+            .handle(&SelectorEvent::button(Button::Confirm))
+            .unwrap()
+            .unwrap();
+        assert_eq!(ranked, vec![&1, &2]);
+    }
+
+    #[test]
+    fn quantity_bounds() {
+        let values: Vec<u32> = (0..3).collect();
+        let mut config = PagedSelectorConfig::default();
+        config.max_select(2);
+        let bounds = |v: &u32| if *v == 0 { (1, 2) } else { (0, 3) };
+        let mut state = QuantitySelectorState::new(config, &values, button, bounds);
+        assert_eq!(state.quantities, vec![1, 0, 0]);
+
+        // Without a focused item the quantity buttons do nothing
+        state.handle(&SelectorEvent::button(Button::Increase));
+        assert_eq!(state.quantities, vec![1, 0, 0]);
+
+        state.handle(&click(0));
+        state.handle(&SelectorEvent::button(Button::Decrease));
+        for _ in 0..3 {
+            state.handle(&SelectorEvent::button(Button::Increase));
+        }
+        assert_eq!(state.quantities[0], 2);
+
+        state.handle(&click(1));
+        state.handle(&SelectorEvent::button(Button::Increase));
+        // A third item would go above max
+        state.handle(&click(2));
+        state.handle(&SelectorEvent::button(Button::Increase));
+        assert_eq!(state.quantities, vec![2, 1, 0]);
+
+        let picked = state
+            .handle(&SelectorEvent::button(Button::Confirm))
+            .unwrap()
+            .unwrap();
+        assert_eq!(picked, HashMap::from([(&values[0], 2), (&values[1], 1)]));
+    }
+
+    #[test]
+    fn grouped_selection() {
+        let groups = vec![
+            ("A".to_string(), vec![1, 2]),
+            ("B".to_string(), vec![3]),
+            ("C".to_string(), vec![]),
+        ];
+        let mut state = GroupedSelectorState::new(Default::default(), &groups, button);
+        let jump = |group: &str| SelectorEvent {
+            custom_id: GROUP_MENU_ID.to_string(),
+            values: vec![group.to_string()],
+            user_id: UserId::default(),
+        };
+
+        state.handle(&click(0));
+        state.handle(&jump("1"));
+        assert_eq!(state.paging.curr_page, 1);
+        assert_eq!(components(&state.render().1)[0][0].0, "_tools_selector_2");
+        state.handle(&click(2));
+        // Empty groups have no page
+        state.handle(&jump("2"));
+        assert_eq!(state.paging.curr_page, 1);
+
+        let selection = state
+            .handle(&SelectorEvent::button(Button::Confirm))
+            .unwrap()
+            .unwrap();
+        let groups = selection
+            .groups()
+            .iter()
+            .map(|(name, s)| (*name, s.iter().map(|v| **v).collect::<Vec<_>>()))
+            .collect::<Vec<_>>();
+        assert_eq!(groups, vec![("A", vec![1]), ("B", vec![3]), ("C", vec![])]);
+        assert_eq!(selection.flatten(), HashSet::from([&1, &3]));
+    }
+
+    #[test]
+    fn long_lists() {
+        let values: Vec<u32> = (0..1000).collect();
+        let mut state = PagedSelectorState::new(Default::default(), &values, button);
+        for _ in 0..25 {
+            state.handle(&SelectorEvent::button(Button::Next));
+        }
+        let emb = &state.render().0[0].0;
+        let titles: Vec<&str> = emb["fields"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|f| f["name"].as_str().unwrap())
+            .collect();
+        assert_eq!(titles.len(), 10);
+        assert_eq!(titles[0], "Page 21");
+        assert_eq!(titles[5], "Page 26 (current)");
+
+        // Long items only fit a few pages, each cut to the field limit
+        let values: Vec<String> = (0..100).map(|i| format!("{:0>200}", i)).collect();
+        let state = PagedSelectorState::new(Default::default(), &values, |v| {
+            (ReactionType::Unicode("🔵".to_string()), v[..10].to_string())
+        });
+        let emb = state.render().0.remove(0).0;
+        let fields = emb["fields"].as_array().unwrap();
+        assert_eq!(fields.len(), 3);
+        let chars: usize = fields
+            .iter()
+            .map(|f| f["value"].as_str().unwrap().chars().count())
+            .sum();
+        assert_eq!(chars, 3 * 1024);
+    }
+}
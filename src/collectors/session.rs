@@ -0,0 +1,314 @@
+use std::{
+    fmt::Display,
+    sync::{Arc, Mutex},
+    time::Instant,
+};
+
+use serenity::{
+    client::Context,
+    model::id::{ChannelId, MessageId, UserId},
+    prelude::TypeMapKey,
+};
+
+use super::CancellationToken;
+
+/// What kind of prompt a session runs
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SessionKind {
+    Selector,
+    Pager,
+    Wizard,
+    Custom(String),
+}
+
+/// Which sessions count against each other for the policy
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SessionScope {
+    /// All sessions of the user
+    User,
+    /// All sessions in the channel
+    Channel,
+    /// Sessions of the user in the channel
+    UserInChannel,
+}
+
+impl SessionScope {
+    fn contains(self, info: &SessionInfo, user_id: UserId, channel_id: ChannelId) -> bool {
+        match self {
+            Self::User => info.user_id == user_id,
+            Self::Channel => info.channel_id == channel_id,
+            Self::UserInChannel => info.user_id == user_id && info.channel_id == channel_id,
+        }
+    }
+}
+
+/// What happens when a new session is started while others are active
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SessionPolicy {
+    /// Refuse the new session
+    RejectNew,
+    /// Cancel the active sessions
+    ReplaceOld,
+    /// Refuse the new session once that many are active
+    AllowN(usize),
+}
+
+#[derive(Clone, Debug)]
+pub struct SessionInfo {
+    pub id: u64,
+    pub user_id: UserId,
+    pub channel_id: ChannelId,
+    pub message_id: Option<MessageId>,
+    pub kind: SessionKind,
+    pub started: Instant,
+}
+
+#[derive(Debug)]
+pub enum SessionError {
+    /// The policy does not allow another session, contains the active ones
+    Rejected(Vec<SessionInfo>),
+}
+
+impl Display for SessionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Rejected(active) => write!(f, "{} sessions already active", active.len()),
+        }
+    }
+}
+
+impl std::error::Error for SessionError {}
+
+struct Entry {
+    info: SessionInfo,
+    token: CancellationToken,
+}
+
+struct Sessions {
+    next_id: u64,
+    entries: Vec<Entry>,
+    policy: SessionPolicy,
+    scope: SessionScope,
+}
+
+/// Tracks the active interactive sessions. Put it into the `TypeMap` of
+/// the client and start a session before running a collector with its
+/// token. Clones share the sessions and settings.
+#[derive(Clone)]
+pub struct SessionRegistry {
+    sessions: Arc<Mutex<Sessions>>,
+}
+
+impl TypeMapKey for SessionRegistry {
+    type Value = SessionRegistry;
+}
+
+impl Default for SessionRegistry {
+    fn default() -> Self {
+        Self {
+            sessions: Arc::new(Mutex::new(Sessions {
+                next_id: 0,
+                entries: Vec::new(),
+                policy: SessionPolicy::ReplaceOld,
+                scope: SessionScope::UserInChannel,
+            })),
+        }
+    }
+}
+
+impl SessionRegistry {
+    /// Applies to sessions started afterwards through any handle
+    pub fn policy(&self, policy: SessionPolicy) -> &Self {
+        self.sessions.lock().unwrap().policy = policy;
+        self
+    }
+
+    pub fn scope(&self, scope: SessionScope) -> &Self {
+        self.sessions.lock().unwrap().scope = scope;
+        self
+    }
+
+    /// The registry in the `TypeMap` of the context
+    pub async fn get(ctx: &Context) -> Option<SessionRegistry> {
+        ctx.data.read().await.get::<SessionRegistry>().cloned()
+    }
+
+    /// Starts a session if the policy allows it. The session ends when
+    /// the returned handle gets dropped.
+    pub fn start(
+        &self,
+        user_id: UserId,
+        channel_id: ChannelId,
+        kind: SessionKind,
+    ) -> Result<Session, SessionError> {
+        let mut sessions = self.sessions.lock().unwrap();
+        let scope = sessions.scope;
+        let active = sessions
+            .entries
+            .iter()
+            .filter(|e| scope.contains(&e.info, user_id, channel_id))
+            .map(|e| e.info.clone())
+            .collect::<Vec<_>>();
+
+        match sessions.policy {
+            SessionPolicy::RejectNew if !active.is_empty() => {
+                return Err(SessionError::Rejected(active))
+            }
+            SessionPolicy::AllowN(n) if active.len() >= n => {
+                return Err(SessionError::Rejected(active))
+            }
+            SessionPolicy::ReplaceOld => {
+                sessions.entries.retain(|e| {
+                    let replaced = scope.contains(&e.info, user_id, channel_id);
+                    if replaced {
+                        e.token.cancel();
+                    }
+                    !replaced
+                });
+            }
+            _ => (),
+        }
+
+        sessions.next_id += 1;
+        let info = SessionInfo {
+            id: sessions.next_id,
+            user_id,
+            channel_id,
+            message_id: None,
+            kind,
+            started: Instant::now(),
+        };
+        let token = CancellationToken::new();
+        sessions.entries.push(Entry {
+            info: info.clone(),
+            token: token.clone(),
+        });
+
+        Ok(Session {
+            id: info.id,
+            token,
+            sessions: self.sessions.clone(),
+        })
+    }
+
+    /// All active sessions
+    pub fn list(&self) -> Vec<SessionInfo> {
+        let sessions = self.sessions.lock().unwrap();
+        sessions.entries.iter().map(|e| e.info.clone()).collect()
+    }
+
+    pub fn list_user(&self, user_id: UserId) -> Vec<SessionInfo> {
+        let mut list = self.list();
+        list.retain(|s| s.user_id == user_id);
+        list
+    }
+
+    pub fn list_channel(&self, channel_id: ChannelId) -> Vec<SessionInfo> {
+        let mut list = self.list();
+        list.retain(|s| s.channel_id == channel_id);
+        list
+    }
+
+    // Cancels and removes the matching sessions, returns how many
+    fn close_where<F: Fn(&SessionInfo) -> bool>(&self, f: F) -> usize {
+        let mut sessions = self.sessions.lock().unwrap();
+        let before = sessions.entries.len();
+        sessions.entries.retain(|e| {
+            let close = f(&e.info);
+            if close {
+                e.token.cancel();
+            }
+            !close
+        });
+        before - sessions.entries.len()
+    }
+
+    /// Cancels the session, returns false if it was not active
+    pub fn close(&self, id: u64) -> bool {
+        self.close_where(|s| s.id == id) > 0
+    }
+
+    pub fn close_user(&self, user_id: UserId) -> usize {
+        self.close_where(|s| s.user_id == user_id)
+    }
+
+    pub fn close_channel(&self, channel_id: ChannelId) -> usize {
+        self.close_where(|s| s.channel_id == channel_id)
+    }
+}
+
+/// Handle of an active session. Pass its token to the collector config.
+pub struct Session {
+    id: u64,
+    token: CancellationToken,
+    sessions: Arc<Mutex<Sessions>>,
+}
+
+impl Session {
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    pub fn token(&self) -> CancellationToken {
+        self.token.clone()
+    }
+
+    /// Records the message the prompt runs on
+    pub fn set_message(&self, message_id: MessageId) {
+        let mut sessions = self.sessions.lock().unwrap();
+        if let Some(e) = sessions.entries.iter_mut().find(|e| e.info.id == self.id) {
+            e.info.message_id = Some(message_id);
+        }
+    }
+}
+
+impl Drop for Session {
+    fn drop(&mut self) {
+        let mut sessions = self.sessions.lock().unwrap();
+        sessions.entries.retain(|e| e.info.id != self.id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn policies() {
+        let (user, channel) = (UserId(1), ChannelId(2));
+        let registry = SessionRegistry::default();
+        // Settings changed through a clone apply to all handles
+        let handle = registry.clone();
+
+        let first = registry
+            .start(user, channel, SessionKind::Selector)
+            .unwrap();
+        let second = registry.start(user, channel, SessionKind::Wizard).unwrap();
+        assert!(first.token().is_cancelled());
+        assert_eq!(registry.list().len(), 1);
+
+        handle.policy(SessionPolicy::RejectNew);
+        assert!(registry.start(user, channel, SessionKind::Pager).is_err());
+        // Other channels are not in the scope
+        let other = registry
+            .start(user, ChannelId(3), SessionKind::Pager)
+            .unwrap();
+
+        handle
+            .scope(SessionScope::User)
+            .policy(SessionPolicy::AllowN(3));
+        let third = registry.start(user, channel, SessionKind::Pager).unwrap();
+        assert!(registry.start(user, channel, SessionKind::Pager).is_err());
+
+        assert_eq!(registry.close_user(user), 3);
+        assert!(second.token().is_cancelled() && other.token().is_cancelled());
+        assert!(third.token().is_cancelled());
+        assert!(registry.list().is_empty());
+
+        drop(first);
+        let fourth = registry.start(user, channel, SessionKind::Pager).unwrap();
+        assert_eq!(registry.list_user(user)[0].id, fourth.id());
+        drop(fourth);
+        assert!(registry.list_channel(channel).is_empty());
+    }
+}
@@ -0,0 +1,496 @@
+use std::{
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+
+use serenity::{
+    builder::{CreateActionRow, CreateButton, CreateEmbed},
+    client::Context,
+    futures::channel::mpsc::UnboundedSender,
+    json::{json, Value},
+    model::{application::component::ButtonStyle, channel::ReactionType, id::UserId},
+};
+
+use super::{
+    store::Persistence, CancellationToken, PagedSelectorError, SelectorEvent, SelectorLimits,
+    SelectorState, SessionStore, UpdatAbleMessage,
+};
+use crate::{builder::CreateEmbedExt, components::Button};
+
+const ROLE_ID: &str = "_tools_signup_";
+// Discord rejects embeds with more fields
+const MAX_FIELDS: usize = 25;
+
+/// A role users can sign up for
+#[derive(Clone, Debug)]
+pub struct SignupRole {
+    name: String,
+    emoji: Option<ReactionType>,
+    // Further sign ups go to the waitlist
+    capacity: Option<usize>,
+}
+
+impl SignupRole {
+    pub fn new<N: ToString>(name: N) -> Self {
+        Self {
+            name: name.to_string(),
+            emoji: None,
+            capacity: None,
+        }
+    }
+
+    pub fn emoji(&mut self, emoji: ReactionType) -> &mut Self {
+        self.emoji = Some(emoji);
+        self
+    }
+
+    pub fn capacity(&mut self, capacity: usize) -> &mut Self {
+        self.capacity = Some(capacity);
+        self
+    }
+}
+
+/// A change of the roster
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SignupEvent {
+    /// Index of the role the user joined
+    Joined(UserId, usize),
+    /// The role was full, the user is on its waitlist
+    Waitlisted(UserId, usize),
+    /// The user moved from the waitlist into the role
+    Promoted(UserId, usize),
+    /// The user left the role or its waitlist
+    Left(UserId, usize),
+}
+
+#[derive(Clone, Debug)]
+pub struct SignupConfig {
+    base_embed: CreateEmbed,
+    // How long sign ups are open
+    duration: Duration,
+    // Names per roster field
+    names_per_field: usize,
+    events: Option<UnboundedSender<SignupEvent>>,
+    cancel_token: Option<CancellationToken>,
+    persistence: Option<Persistence>,
+}
+
+impl Default for SignupConfig {
+    fn default() -> Self {
+        Self {
+            base_embed: Default::default(),
+            duration: Duration::from_secs(3600),
+            names_per_field: 10,
+            events: None,
+            cancel_token: None,
+            persistence: None,
+        }
+    }
+}
+
+impl SignupConfig {
+    pub fn base_embed(&mut self, base_embed: CreateEmbed) -> &mut Self {
+        self.base_embed = base_embed;
+        self
+    }
+
+    pub fn duration(&mut self, duration: Duration) -> &mut Self {
+        self.duration = duration;
+        self
+    }
+
+    pub fn names_per_field(&mut self, count: usize) -> &mut Self {
+        self.names_per_field = count;
+        self
+    }
+
+    /// Every change of the roster gets sent here, e.g. to persist it
+    pub fn events(&mut self, events: UnboundedSender<SignupEvent>) -> &mut Self {
+        self.events = Some(events);
+        self
+    }
+
+    /// Ends the sign up early once the token gets cancelled
+    pub fn cancel_token(&mut self, token: CancellationToken) -> &mut Self {
+        self.cancel_token = Some(token);
+        self
+    }
+
+    /// Saves the roster after every change, so the sign up can be resumed
+    /// on the same message after a restart
+    pub fn persist<K: ToString>(&mut self, store: Arc<dyn SessionStore>, kind: K) -> &mut Self {
+        self.persistence = Some(Persistence {
+            store,
+            kind: kind.to_string(),
+        });
+        self
+    }
+}
+
+/// Members and waitlist of each role
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SignupRoster {
+    members: Vec<Vec<UserId>>,
+    waitlist: Vec<Vec<UserId>>,
+}
+
+impl SignupRoster {
+    /// Members of the role in the order they joined, empty for unknown
+    /// roles
+    pub fn members(&self, role: usize) -> &[UserId] {
+        self.members.get(role).map_or(&[], Vec::as_slice)
+    }
+
+    /// Waiting users of the role in the order they signed up, empty for
+    /// unknown roles
+    pub fn waitlist(&self, role: usize) -> &[UserId] {
+        self.waitlist.get(role).map_or(&[], Vec::as_slice)
+    }
+
+    // The role and whether the user is waiting for it
+    fn find(&self, user: UserId) -> Option<(usize, bool)> {
+        let find = |lists: &[Vec<UserId>]| lists.iter().position(|l| l.contains(&user));
+        find(&self.members)
+            .map(|r| (r, false))
+            .or_else(|| find(&self.waitlist).map(|r| (r, true)))
+    }
+}
+
+/// Sign up logic, can be driven with [`UpdatAbleMessage::run_selector`].
+/// Never finishes by itself.
+pub struct SignupState {
+    config: SignupConfig,
+    roles: Vec<SignupRole>,
+    roster: SignupRoster,
+    // unix time sign ups close, kept on resume
+    closes: u64,
+}
+
+impl SignupState {
+    /// Only the first 20 roles are used, the last row holds the leave button
+    pub fn new(config: SignupConfig, roles: &[SignupRole]) -> Self {
+        let roles = roles.iter().take(20).cloned().collect::<Vec<_>>();
+        let closes = now() + config.duration;
+        Self {
+            closes: closes.as_secs(),
+            config,
+            roster: SignupRoster {
+                members: vec![Vec::new(); roles.len()],
+                waitlist: vec![Vec::new(); roles.len()],
+            },
+            roles,
+        }
+    }
+
+    pub fn roster(&self) -> &SignupRoster {
+        &self.roster
+    }
+
+    pub fn into_roster(self) -> SignupRoster {
+        self.roster
+    }
+
+    // Time left until sign ups close
+    fn remaining(&self) -> Duration {
+        Duration::from_secs(self.closes).saturating_sub(now())
+    }
+
+    fn send(&self, event: SignupEvent) {
+        if let Some(events) = &self.config.events {
+            // The receiver might not care anymore
+            let _ = events.unbounded_send(event);
+        }
+    }
+
+    fn leave(&mut self, user: UserId) {
+        let (role, waiting) = match self.roster.find(user) {
+            Some(found) => found,
+            None => return,
+        };
+        if waiting {
+            self.roster.waitlist[role].retain(|u| *u != user);
+        } else {
+            self.roster.members[role].retain(|u| *u != user);
+            if !self.roster.waitlist[role].is_empty() {
+                let promoted = self.roster.waitlist[role].remove(0);
+                self.roster.members[role].push(promoted);
+                self.send(SignupEvent::Left(user, role));
+                self.send(SignupEvent::Promoted(promoted, role));
+                return;
+            }
+        }
+        self.send(SignupEvent::Left(user, role));
+    }
+
+    fn join(&mut self, user: UserId, role: usize) {
+        if self.roster.find(user).map(|(r, _)| r) == Some(role) {
+            return;
+        }
+        self.leave(user);
+        let full = self.roles[role]
+            .capacity
+            .is_some_and(|c| self.roster.members[role].len() >= c);
+        if full {
+            self.roster.waitlist[role].push(user);
+            self.send(SignupEvent::Waitlisted(user, role));
+        } else {
+            self.roster.members[role].push(user);
+            self.send(SignupEvent::Joined(user, role));
+        }
+    }
+}
+
+fn now() -> Duration {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+}
+
+impl SelectorState for SignupState {
+    type Output = SignupRoster;
+
+    fn render(&self) -> (Vec<CreateEmbed>, Vec<CreateActionRow>) {
+        let mention = |u: &UserId| format!("<@{}>", u);
+        let per_field = self.config.names_per_field.max(1);
+        let mut emb = self.config.base_embed.clone();
+
+        let base_fields = emb
+            .0
+            .get("fields")
+            .and_then(Value::as_array)
+            .map_or(0, Vec::len);
+        let needed = (0..self.roles.len())
+            .map(|i| {
+                let waiting = self.roster.waitlist[i].len().div_ceil(per_field);
+                self.roster.members[i].len().div_ceil(per_field).max(1) + waiting
+            })
+            .sum::<usize>();
+        // Too many fields, every role gets one with the first names
+        let compact = base_fields + needed > MAX_FIELDS;
+
+        let shown_roles = MAX_FIELDS.saturating_sub(base_fields);
+        for (i, role) in self.roles.iter().enumerate().take(shown_roles) {
+            let members = &self.roster.members[i];
+            let waiting = &self.roster.waitlist[i];
+            let title = match role.capacity {
+                Some(c) => format!("{} ({}/{})", role.name, members.len(), c),
+                None => format!("{} ({})", role.name, members.len()),
+            };
+
+            if compact {
+                let mut names = members
+                    .iter()
+                    .take(per_field)
+                    .map(mention)
+                    .collect::<Vec<_>>();
+                if members.len() > per_field {
+                    names.push(format!("+{} more", members.len() - per_field));
+                }
+                if !waiting.is_empty() {
+                    names.push(format!("{} waiting", waiting.len()));
+                }
+                let text = match names.is_empty() {
+                    true => "-".to_string(),
+                    false => names.join("\n"),
+                };
+                emb.field(title, text, true);
+                continue;
+            }
+
+            if members.is_empty() {
+                emb.field(title, "-", true);
+            } else {
+                emb.fields_chunked_fmt(members, mention, &title, true, per_field);
+            }
+            if !waiting.is_empty() {
+                let title = format!("{} waitlist", role.name);
+                emb.fields_chunked_fmt(waiting, mention, &title, true, per_field);
+            }
+        }
+
+        let mut rows = Vec::new();
+        for (r, chunk) in self.roles.chunks(5).enumerate() {
+            let mut ar = CreateActionRow::default();
+            for (i, role) in chunk.iter().enumerate() {
+                let mut b = CreateButton::default();
+                b.custom_id(format!("{}{}", ROLE_ID, r * 5 + i))
+                    .label(&role.name)
+                    .style(ButtonStyle::Primary);
+                if let Some(emoji) = &role.emoji {
+                    b.emoji(emoji.clone());
+                }
+                ar.add_button(b);
+            }
+            rows.push(ar);
+        }
+        let mut ar = CreateActionRow::default();
+        ar.add_button(Button::Leave.create());
+        rows.push(ar);
+
+        (vec![emb], rows)
+    }
+
+    fn handle(
+        &mut self,
+        event: &SelectorEvent,
+    ) -> Option<Result<SignupRoster, PagedSelectorError>> {
+        if event.custom_id == Button::Leave.id() {
+            self.leave(event.user_id);
+        } else if let Some(role) = event.custom_id.strip_prefix(ROLE_ID) {
+            if let Some(role) = role.parse().ok().filter(|r| *r < self.roles.len()) {
+                self.join(event.user_id, role);
+            }
+        }
+        None
+    }
+
+    fn save(&self) -> Value {
+        json!({ "roster": self.roster, "closes": self.closes })
+    }
+
+    fn restore(&mut self, saved: &Value) {
+        if let Ok(roster) = serde_json::from_value::<SignupRoster>(saved["roster"].clone()) {
+            let roles = self.roles.len();
+            if roster.members.len() == roles && roster.waitlist.len() == roles {
+                self.roster = roster;
+            }
+        }
+        if let Some(closes) = saved["closes"].as_u64() {
+            self.closes = closes;
+        }
+    }
+}
+
+impl UpdatAbleMessage {
+    /// Lets users join one of the roles, switch or leave until the
+    /// duration passed. Full roles put users on a waitlist, they get
+    /// promoted in order when a member leaves. Returns the final roster.
+    /// A resumed sign up closes at the originally planned time.
+    pub async fn signup(
+        &mut self,
+        ctx: &Context,
+        config: SignupConfig,
+        roles: &[SignupRole],
+    ) -> Result<SignupRoster, PagedSelectorError> {
+        let mut limits = SelectorLimits::default();
+        if let Some(token) = &config.cancel_token {
+            limits.cancel_token(token.clone());
+        }
+        limits.persistence = config.persistence.clone();
+
+        let mut state = SignupState::new(config, roles);
+        // The close time is needed for the limits, restoring it again in
+        // run_selector does not change anything
+        if let Some(saved) = match &limits.persistence {
+            Some(p) => p.load(self.msg())?,
+            None => None,
+        } {
+            state.restore(&saved["state"]);
+        }
+        let open = state.remaining();
+        limits.timeout(open).deadline(open);
+
+        match self.run_selector(ctx, &mut state, &limits).await {
+            Err(PagedSelectorError::TimedOut | PagedSelectorError::DeadlineExceeded) => {
+                Ok(state.into_roster())
+            }
+            res => res,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serenity::futures::channel::mpsc::unbounded;
+
+    #[test]
+    fn waitlist() {
+        let (a, b, c) = (UserId(1), UserId(2), UserId(3));
+        let (tx, mut rx) = unbounded();
+        let mut config = SignupConfig::default();
+        config.events(tx);
+        let mut tank = SignupRole::new("tank");
+        tank.capacity(1);
+        let mut state = SignupState::new(config, &[tank, SignupRole::new("dps")]);
+
+        let click = |id: &str, user| SelectorEvent::click(id).by(user);
+        state.handle(&click("_tools_signup_0", a));
+        state.handle(&click("_tools_signup_0", b));
+        state.handle(&click("_tools_signup_1", c));
+        assert_eq!(state.roster().members(0), &[a]);
+        assert_eq!(state.roster().waitlist(0), &[b]);
+
+        // Switching the role frees the spot for the waitlist
+        state.handle(&click("_tools_signup_1", a));
+        state.handle(&SelectorEvent::button(Button::Leave).by(c));
+        assert_eq!(state.roster().members(0), &[b]);
+        assert_eq!(state.roster().members(1), &[a]);
+
+        assert!(state.roster().members(5).is_empty());
+
+        let mut events = Vec::new();
+        while let Ok(e) = rx.try_recv() {
+            events.push(e);
+        }
+        assert_eq!(
+            events,
+            vec![
+                SignupEvent::Joined(a, 0),
+                SignupEvent::Waitlisted(b, 0),
+                SignupEvent::Joined(c, 1),
+                SignupEvent::Left(a, 0),
+                SignupEvent::Promoted(b, 0),
+                SignupEvent::Joined(a, 1),
+                SignupEvent::Left(c, 1),
+            ]
+        );
+    }
+
+    #[test]
+    fn field_limit() {
+        let roles = (0..20)
+            .map(|i| {
+                let mut role = SignupRole::new(format!("role{}", i));
+                role.capacity(1);
+                role
+            })
+            .collect::<Vec<_>>();
+        let mut state = SignupState::new(SignupConfig::default(), &roles);
+        // Every role has a member and a waitlist, 40 fields uncompacted
+        for user in 0..40 {
+            let id = format!("_tools_signup_{}", user % 20);
+            state.handle(&SelectorEvent::click(&id).by(UserId(user + 1)));
+        }
+        let fields = |state: &SignupState| {
+            let (emb, _) = state.render();
+            emb[0].0["fields"].as_array().unwrap().clone()
+        };
+        assert_eq!(fields(&state).len(), 20);
+
+        let mut tank = SignupRole::new("tank");
+        tank.capacity(260);
+        let mut state = SignupState::new(SignupConfig::default(), &[tank]);
+        for user in 1..=300 {
+            state.handle(&SelectorEvent::click("_tools_signup_0").by(UserId(user)));
+        }
+        let fields = fields(&state);
+        assert_eq!(fields.len(), 1);
+        let names = fields[0]["value"].as_str().unwrap();
+        assert!(names.ends_with("+250 more\n40 waiting"));
+    }
+
+    #[test]
+    fn resumed_close_time() {
+        let mut config = SignupConfig::default();
+        config.duration(Duration::from_secs(60));
+        let saved = SignupState::new(config.clone(), &[SignupRole::new("dps")]).save();
+
+        config.duration(Duration::from_secs(3600));
+        let mut state = SignupState::new(config, &[SignupRole::new("dps")]);
+        state.restore(&saved);
+        assert!(state.remaining() <= Duration::from_secs(60));
+    }
+}
@@ -0,0 +1,257 @@
+use std::{
+    collections::HashMap,
+    fs,
+    future::Future,
+    io,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+};
+
+use serde::{Deserialize, Serialize};
+use serenity::{
+    client::Context,
+    json::Value,
+    model::{
+        channel::Message,
+        id::{ChannelId, MessageId},
+    },
+    Error as SerenityError,
+};
+use tracing::warn;
+
+/// Progress of an interactive message that can be resumed
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct StoredSession {
+    pub message_id: MessageId,
+    pub channel_id: ChannelId,
+    /// Set by the bot to know how to rebuild the collector on resume
+    pub kind: String,
+    /// Saved by the selector state or wizard
+    pub state: Value,
+}
+
+/// Persists sessions keyed by their message. This crate only ships a
+/// memory and a file store, a database backed store implements this trait
+/// in the bot.
+pub trait SessionStore: Send + Sync {
+    fn save(&self, session: &StoredSession) -> io::Result<()>;
+
+    fn load(&self, message_id: MessageId) -> io::Result<Option<StoredSession>>;
+
+    fn remove(&self, message_id: MessageId) -> io::Result<()>;
+
+    fn load_all(&self) -> io::Result<Vec<StoredSession>>;
+}
+
+/// Keeps sessions only as long as the process runs
+#[derive(Default)]
+pub struct MemorySessionStore {
+    sessions: Mutex<HashMap<MessageId, StoredSession>>,
+}
+
+impl SessionStore for MemorySessionStore {
+    fn save(&self, session: &StoredSession) -> io::Result<()> {
+        let mut sessions = self.sessions.lock().unwrap();
+        sessions.insert(session.message_id, session.clone());
+        Ok(())
+    }
+
+    fn load(&self, message_id: MessageId) -> io::Result<Option<StoredSession>> {
+        Ok(self.sessions.lock().unwrap().get(&message_id).cloned())
+    }
+
+    fn remove(&self, message_id: MessageId) -> io::Result<()> {
+        self.sessions.lock().unwrap().remove(&message_id);
+        Ok(())
+    }
+
+    fn load_all(&self) -> io::Result<Vec<StoredSession>> {
+        Ok(self.sessions.lock().unwrap().values().cloned().collect())
+    }
+}
+
+/// Stores every session as a JSON file in a directory
+pub struct FileSessionStore {
+    dir: PathBuf,
+}
+
+impl FileSessionStore {
+    /// Creates the directory if needed
+    pub fn new<P: Into<PathBuf>>(dir: P) -> io::Result<Self> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir)?;
+        Ok(Self { dir })
+    }
+
+    fn path(&self, message_id: MessageId) -> PathBuf {
+        self.dir.join(format!("{}.json", message_id))
+    }
+}
+
+impl SessionStore for FileSessionStore {
+    fn save(&self, session: &StoredSession) -> io::Result<()> {
+        let path = self.path(session.message_id);
+        // Write and rename, so a crash never leaves half a file
+        let tmp = path.with_extension("tmp");
+        fs::write(&tmp, serde_json::to_vec(session)?)?;
+        fs::rename(tmp, path)
+    }
+
+    fn load(&self, message_id: MessageId) -> io::Result<Option<StoredSession>> {
+        match fs::read(self.path(message_id)) {
+            Ok(bytes) => Ok(Some(serde_json::from_slice(&bytes)?)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    fn remove(&self, message_id: MessageId) -> io::Result<()> {
+        match fs::remove_file(self.path(message_id)) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        }
+    }
+
+    /// Files that can not be read are skipped, so one broken session
+    /// does not stop the others from resuming. Leftover `.tmp` files of
+    /// interrupted saves are ignored.
+    fn load_all(&self) -> io::Result<Vec<StoredSession>> {
+        let mut sessions = Vec::new();
+        for entry in fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            if path.extension().is_none_or(|e| e != "json") {
+                continue;
+            }
+            let session = fs::read(&path).and_then(|bytes| Ok(serde_json::from_slice(&bytes)?));
+            match session {
+                Ok(session) => sessions.push(session),
+                Err(e) => warn!("Skipping session file {}: {}", path.display(), e),
+            }
+        }
+        Ok(sessions)
+    }
+}
+
+/// Where and as what a collector persists its progress
+#[derive(Clone)]
+pub(crate) struct Persistence {
+    pub(crate) store: Arc<dyn SessionStore>,
+    pub(crate) kind: String,
+}
+
+impl std::fmt::Debug for Persistence {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Persistence")
+            .field("kind", &self.kind)
+            .finish()
+    }
+}
+
+impl Persistence {
+    /// Saved progress of this kind on the message
+    pub(crate) fn load(&self, msg: &Message) -> io::Result<Option<Value>> {
+        Ok(self
+            .store
+            .load(msg.id)?
+            .filter(|s| s.kind == self.kind)
+            .map(|s| s.state))
+    }
+
+    pub(crate) fn save(&self, msg: &Message, state: Value) -> io::Result<()> {
+        self.store.save(&StoredSession {
+            message_id: msg.id,
+            channel_id: msg.channel_id,
+            kind: self.kind.clone(),
+            state,
+        })
+    }
+
+    pub(crate) fn remove(&self, msg: &Message) -> io::Result<()> {
+        self.store.remove(msg.id)
+    }
+}
+
+/// Rebinds collectors to the stored sessions after a restart. `resume`
+/// gets spawned for every session whose message still exists and has to
+/// run the same collector of the stored kind on it again, which then
+/// continues with the stored progress. Sessions of deleted messages are
+/// removed. Returns the number of resumed sessions.
+///
+/// Only collectors on normal messages can be resumed, interaction
+/// responses can not be edited anymore after a restart.
+pub async fn resume_sessions<F, Fut>(
+    ctx: &Context,
+    store: Arc<dyn SessionStore>,
+    resume: F,
+) -> io::Result<usize>
+where
+    F: Fn(Context, Message, StoredSession) -> Fut,
+    Fut: Future<Output = ()> + Send + 'static,
+{
+    let mut resumed = 0;
+    for session in store.load_all()? {
+        match ctx
+            .http
+            .get_message(session.channel_id.0, session.message_id.0)
+            .await
+        {
+            Ok(msg) => {
+                tokio::spawn(resume(ctx.clone(), msg, session));
+                resumed += 1;
+            }
+            Err(SerenityError::Http(e)) if e.status_code().is_some_and(|s| s.as_u16() == 404) => {
+                store.remove(session.message_id)?
+            }
+            // Might work on the next start
+            Err(_) => (),
+        }
+    }
+    Ok(resumed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serenity::json::json;
+
+    #[test]
+    fn file_store() {
+        let dir = std::env::temp_dir().join(format!("tools_sessions_{}", std::process::id()));
+        let store = FileSessionStore::new(&dir).unwrap();
+        let session = StoredSession {
+            message_id: MessageId(2),
+            channel_id: ChannelId(1),
+            kind: "selector".to_string(),
+            state: json!({ "page": 1, "selected": [3] }),
+        };
+
+        store.save(&session).unwrap();
+        assert_eq!(store.load(MessageId(2)).unwrap(), Some(session.clone()));
+        assert_eq!(store.load_all().unwrap(), vec![session]);
+        store.remove(MessageId(2)).unwrap();
+        store.remove(MessageId(2)).unwrap();
+        assert_eq!(store.load(MessageId(2)).unwrap(), None);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn broken_files() {
+        let dir = std::env::temp_dir().join(format!("tools_broken_{}", std::process::id()));
+        let store = FileSessionStore::new(&dir).unwrap();
+        let session = StoredSession {
+            message_id: MessageId(2),
+            channel_id: ChannelId(1),
+            kind: "selector".to_string(),
+            state: json!({}),
+        };
+        store.save(&session).unwrap();
+        // Left behind by a crash while saving
+        fs::write(dir.join("3.json"), b"{\"message_id\": ").unwrap();
+        fs::write(dir.join("4.tmp"), b"{").unwrap();
+
+        assert_eq!(store.load_all().unwrap(), vec![session]);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,131 @@
+use std::time::Duration;
+
+use serenity::{
+    builder::{CreateActionRow, CreateEmbed},
+    client::Context,
+    http::HttpError,
+    json::JsonMap,
+    model::application::interaction::message_component::MessageComponentInteraction,
+    Error as SerenityError, Result as SerenityResult,
+};
+use tokio::time::{sleep_until, Instant};
+
+use super::{edit_payload, UpdatAbleMessage};
+use crate::interactions::MessageComponentInteractionExt;
+
+// Discord error code of a second response to an interaction
+const ALREADY_ACKNOWLEDGED: isize = 40060;
+
+/// Coalesces edits of a message. Only the latest scheduled render gets
+/// sent, at most once per interval, and only if it differs from what was
+/// sent before. Clicks are preferably answered with the render itself,
+/// editing the message is the fallback.
+#[derive(Debug)]
+pub struct UpdateScheduler {
+    interval: Duration,
+    last_edit: Option<Instant>,
+    // payload of the last edit
+    sent: Option<JsonMap>,
+    pending: Option<(Vec<CreateEmbed>, Vec<CreateActionRow>)>,
+}
+
+impl UpdateScheduler {
+    pub fn new(interval: Duration) -> Self {
+        Self {
+            interval,
+            last_edit: None,
+            sent: None,
+            pending: None,
+        }
+    }
+
+    /// Replaces the pending render
+    pub fn schedule(&mut self, embeds: Vec<CreateEmbed>, rows: Vec<CreateActionRow>) {
+        self.pending = Some((embeds, rows));
+    }
+
+    pub fn is_pending(&self) -> bool {
+        self.pending.is_some()
+    }
+
+    /// Resolves once the next edit may be sent
+    pub async fn ready(&self) {
+        if let Some(last_edit) = self.last_edit {
+            sleep_until(last_edit + self.interval).await;
+        }
+    }
+
+    /// Answers the click with the pending render in a single request if
+    /// the interval since the last edit passed. Otherwise, without changes
+    /// or if answering with the render fails, the click only gets
+    /// acknowledged and the render stays pending for the next flush. A
+    /// click that turns out to be acknowledged already counts as answered.
+    pub async fn respond(
+        &mut self,
+        ctx: &Context,
+        mci: &MessageComponentInteraction,
+    ) -> SerenityResult<()> {
+        let due = self
+            .last_edit
+            .is_none_or(|last_edit| last_edit.elapsed() >= self.interval);
+        let (embeds, rows) = match self.pending.take() {
+            Some(pending) if due => pending,
+            pending => {
+                self.pending = pending;
+                return mci.defer(ctx).await;
+            }
+        };
+        let payload = edit_payload(&embeds, &rows);
+        if self.sent.as_ref() == Some(&payload) {
+            return mci.defer(ctx).await;
+        }
+
+        match mci.update_message(ctx, embeds.clone(), rows.clone()).await {
+            Ok(()) => {
+                self.sent = Some(payload);
+                self.last_edit = Some(Instant::now());
+                Ok(())
+            }
+            Err(e) => {
+                self.pending = Some((embeds, rows));
+                match mci.defer(ctx).await {
+                    // The update reached Discord, only its response got lost
+                    Err(defer) if already_acknowledged(&defer) => Ok(()),
+                    res => res.map_err(|_| e),
+                }
+            }
+        }
+    }
+
+    /// Sends the pending render now if it changed anything. Returns
+    /// whether the message got edited.
+    pub async fn flush(
+        &mut self,
+        ctx: &Context,
+        msg: &mut UpdatAbleMessage,
+    ) -> SerenityResult<bool> {
+        let (embeds, rows) = match self.pending.take() {
+            Some(pending) => pending,
+            None => return Ok(false),
+        };
+        let payload = edit_payload(&embeds, &rows);
+        if self.sent.as_ref() == Some(&payload) {
+            return Ok(false);
+        }
+
+        msg.update(ctx, embeds, rows).await?;
+        self.sent = Some(payload);
+        self.last_edit = Some(Instant::now());
+        Ok(true)
+    }
+}
+
+fn already_acknowledged(e: &SerenityError) -> bool {
+    match e {
+        SerenityError::Http(e) => matches!(
+            &**e,
+            HttpError::UnsuccessfulRequest(r) if r.error.code == ALREADY_ACKNOWLEDGED
+        ),
+        _ => false,
+    }
+}
@@ -0,0 +1,683 @@
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    fmt::Display,
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+use serenity::{
+    builder::{CreateActionRow, CreateButton, CreateEmbed},
+    client::Context,
+    futures::StreamExt,
+    json::{json, Value},
+    model::{
+        application::{
+            component::{ActionRowComponent, ButtonStyle, InputTextStyle},
+            interaction::InteractionResponseType,
+        },
+        channel::Message,
+    },
+    Error as SerenityError,
+};
+use tokio::{select, time::sleep};
+
+use super::{
+    cancelled, expiry_field, store::Persistence, CancellationToken, ExpiryBehaviour, SessionStore,
+    UpdatAbleMessage,
+};
+use crate::{
+    builder::CreateActionRowExt, components::Button, interactions::MessageComponentInteractionExt,
+};
+
+const SELECT_ID: &str = "_tools_wizard_select";
+const CHOICE_ID: &str = "_tools_wizard_choice_";
+const INPUT_ID: &str = "_tools_wizard_input";
+const MODAL_ID: &str = "_tools_wizard_modal";
+
+type Validator = Box<dyn Fn(&WizardAnswer) -> Result<(), String> + Send + Sync>;
+
+/// The kind of input a wizard step asks for
+#[derive(Clone, Debug)]
+pub enum WizardStep {
+    /// Pick between min and max options from a select menu. At most 25 options.
+    Select {
+        options: Vec<String>,
+        min: usize,
+        max: usize,
+    },
+    /// Pick exactly one option with a button. At most 20 options.
+    Choice { options: Vec<String> },
+    /// Enter text through a modal
+    Input {
+        label: String,
+        style: InputTextStyle,
+    },
+    /// Confirm the text shown
+    Confirm { text: String },
+}
+
+/// Answer given to a step
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum WizardAnswer {
+    /// Indices of the selected options
+    Selected(Vec<usize>),
+    /// Index of the chosen option
+    Choice(usize),
+    Input(String),
+    Confirmed,
+}
+
+/// All answers of a wizard, looked up by the key of their step
+#[derive(Clone, Debug, Default)]
+pub struct WizardAnswers {
+    answers: HashMap<String, WizardAnswer>,
+}
+
+impl WizardAnswers {
+    pub fn get(&self, key: &str) -> Option<&WizardAnswer> {
+        self.answers.get(key)
+    }
+
+    pub fn selected(&self, key: &str) -> Option<&[usize]> {
+        match self.answers.get(key) {
+            Some(WizardAnswer::Selected(s)) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn choice(&self, key: &str) -> Option<usize> {
+        match self.answers.get(key) {
+            Some(WizardAnswer::Choice(c)) => Some(*c),
+            _ => None,
+        }
+    }
+
+    pub fn input(&self, key: &str) -> Option<&str> {
+        match self.answers.get(key) {
+            Some(WizardAnswer::Input(i)) => Some(i),
+            _ => None,
+        }
+    }
+
+    pub fn confirmed(&self, key: &str) -> bool {
+        matches!(self.answers.get(key), Some(WizardAnswer::Confirmed))
+    }
+}
+
+struct Step {
+    key: String,
+    title: String,
+    kind: WizardStep,
+    validate: Option<Validator>,
+}
+
+impl Step {
+    // The given answer, or nothing selected for selects that allow it
+    fn answer<'a>(&self, answers: &'a WizardAnswers) -> Option<Cow<'a, WizardAnswer>> {
+        match (answers.get(&self.key), &self.kind) {
+            (Some(answer), _) => Some(Cow::Borrowed(answer)),
+            (None, WizardStep::Select { min: 0, .. }) => {
+                Some(Cow::Owned(WizardAnswer::Selected(Vec::new())))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Chains several prompts on a single message. Every step can be left
+/// with back and the whole wizard with abort.
+pub struct Wizard {
+    base_embed: CreateEmbed,
+    // Gets reset after every input
+    timeout: Duration,
+    expiry: ExpiryBehaviour,
+    show_expiry: bool,
+    steps: Vec<Step>,
+    // stops the wizard from outside
+    cancel_token: Option<CancellationToken>,
+    // stores the progress to resume it
+    persistence: Option<Persistence>,
+}
+
+impl Default for Wizard {
+    fn default() -> Self {
+        Self {
+            base_embed: Default::default(),
+            timeout: Duration::from_secs(60),
+            expiry: ExpiryBehaviour::DisableComponents,
+            show_expiry: false,
+            steps: Vec::new(),
+            cancel_token: None,
+            persistence: None,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum WizardError {
+    TimedOut,
+    Aborted,
+    /// The cancel token was cancelled
+    Cancelled,
+    /// The session store failed
+    Store(std::io::Error),
+    /// The result could not be built from the answers
+    Invalid(String),
+    Serenity(serenity::Error),
+}
+
+impl Display for WizardError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::TimedOut => write!(f, "Wizard timed out"),
+            Self::Aborted => write!(f, "Wizard was aborted"),
+            Self::Cancelled => write!(f, "Wizard was cancelled"),
+            Self::Invalid(e) => write!(f, "Wizard result invalid: {}", e),
+            Self::Store(e) => e.fmt(f),
+            Self::Serenity(e) => e.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for WizardError {}
+
+impl From<std::io::Error> for WizardError {
+    fn from(e: std::io::Error) -> Self {
+        Self::Store(e)
+    }
+}
+
+impl From<SerenityError> for WizardError {
+    fn from(e: SerenityError) -> Self {
+        Self::Serenity(e)
+    }
+}
+
+impl Wizard {
+    pub fn base_embed(&mut self, base_embed: CreateEmbed) -> &mut Self {
+        self.base_embed = base_embed;
+        self
+    }
+
+    pub fn timeout(&mut self, timeout: Duration) -> &mut Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// What happens to the message on timeout. Disables the components
+    /// by default.
+    pub fn expiry(&mut self, expiry: ExpiryBehaviour) -> &mut Self {
+        self.expiry = expiry;
+        self
+    }
+
+    /// Adds a relative timestamp of when the wizard expires to the embed
+    pub fn show_expiry(&mut self, show: bool) -> &mut Self {
+        self.show_expiry = show;
+        self
+    }
+
+    /// Stops the wizard with [`WizardError::Cancelled`] once the token
+    /// gets cancelled
+    pub fn cancel_token(&mut self, token: CancellationToken) -> &mut Self {
+        self.cancel_token = Some(token);
+        self
+    }
+
+    /// Saves the answers after every step, so the wizard can be resumed
+    /// on the same message after a restart. The answers are kept if the
+    /// cancel token stops the wizard.
+    pub fn persist<K: ToString>(&mut self, store: Arc<dyn SessionStore>, kind: K) -> &mut Self {
+        self.persistence = Some(Persistence {
+            store,
+            kind: kind.to_string(),
+        });
+        self
+    }
+
+    /// Adds a step. A select step asking for more than `max` options at
+    /// minimum gets its `min` lowered to `max`.
+    pub fn step<K: ToString, T: ToString>(
+        &mut self,
+        key: K,
+        title: T,
+        mut kind: WizardStep,
+    ) -> &mut Self {
+        if let WizardStep::Select { min, max, .. } = &mut kind {
+            *min = (*min).min(*max);
+        }
+        self.steps.push(Step {
+            key: key.to_string(),
+            title: title.to_string(),
+            kind,
+            validate: None,
+        });
+        self
+    }
+
+    pub fn select<K: ToString, T: ToString>(
+        &mut self,
+        key: K,
+        title: T,
+        options: Vec<String>,
+        min: usize,
+        max: usize,
+    ) -> &mut Self {
+        self.step(key, title, WizardStep::Select { options, min, max })
+    }
+
+    pub fn choice<K: ToString, T: ToString>(
+        &mut self,
+        key: K,
+        title: T,
+        options: Vec<String>,
+    ) -> &mut Self {
+        self.step(key, title, WizardStep::Choice { options })
+    }
+
+    pub fn input<K: ToString, T: ToString, L: ToString>(
+        &mut self,
+        key: K,
+        title: T,
+        label: L,
+        style: InputTextStyle,
+    ) -> &mut Self {
+        let label = label.to_string();
+        self.step(key, title, WizardStep::Input { label, style })
+    }
+
+    pub fn confirm<K: ToString, T: ToString, D: ToString>(
+        &mut self,
+        key: K,
+        title: T,
+        text: D,
+    ) -> &mut Self {
+        let text = text.to_string();
+        self.step(key, title, WizardStep::Confirm { text })
+    }
+
+    /// Validates the answer of the last added step. The error is shown
+    /// to the user who stays on the step.
+    pub fn validate<F>(&mut self, validate: F) -> &mut Self
+    where
+        F: Fn(&WizardAnswer) -> Result<(), String> + Send + Sync + 'static,
+    {
+        if let Some(step) = self.steps.last_mut() {
+            step.validate = Some(Box::new(validate));
+        }
+        self
+    }
+
+    /// Runs the wizard on the message. After the last step `finish` builds
+    /// the result from all answers. If it fails the error is shown and the
+    /// user stays on the last step. With persistence the stored progress on
+    /// this message is restored first.
+    pub async fn run<R, F>(
+        &self,
+        ctx: &Context,
+        msg: &mut UpdatAbleMessage,
+        finish: F,
+    ) -> Result<R, WizardError>
+    where
+        F: Fn(&WizardAnswers) -> Result<R, String> + Send + Sync,
+        R: Send,
+    {
+        let persistence = match &self.persistence {
+            Some(p) => p,
+            None => return self.drive(ctx, msg, finish, 0, Default::default()).await,
+        };
+        let (curr_step, answers) = match persistence.load(msg.msg())? {
+            Some(saved) => self.restore(&saved),
+            None => (0, Default::default()),
+        };
+        let res = self.drive(ctx, msg, finish, curr_step, answers).await;
+        // Keep the progress if only Discord could not be reached or the
+        // token got cancelled, e.g. on shutdown
+        if !matches!(res, Err(WizardError::Serenity(_) | WizardError::Cancelled)) {
+            persistence.remove(msg.msg())?;
+        }
+        res
+    }
+
+    // Only answers of steps with the same key and kind are kept
+    fn restore(&self, saved: &Value) -> (usize, WizardAnswers) {
+        let mut answers = WizardAnswers::default();
+        let saved_answers =
+            serde_json::from_value::<HashMap<String, WizardAnswer>>(saved["answers"].clone())
+                .unwrap_or_default();
+        for step in &self.steps {
+            if let Some(answer) = saved_answers.get(&step.key) {
+                let fits = matches!(
+                    (&step.kind, answer),
+                    (WizardStep::Select { .. }, WizardAnswer::Selected(_))
+                        | (WizardStep::Choice { .. }, WizardAnswer::Choice(_))
+                        | (WizardStep::Input { .. }, WizardAnswer::Input(_))
+                        | (WizardStep::Confirm { .. }, WizardAnswer::Confirmed)
+                );
+                if fits {
+                    answers.answers.insert(step.key.clone(), answer.clone());
+                }
+            }
+        }
+        let step = saved["step"].as_u64().unwrap_or(0) as usize;
+        (step.min(self.steps.len().saturating_sub(1)), answers)
+    }
+
+    fn save(
+        &self,
+        msg: &Message,
+        curr_step: usize,
+        answers: &WizardAnswers,
+    ) -> std::io::Result<()> {
+        if let Some(p) = &self.persistence {
+            p.save(
+                msg,
+                json!({ "step": curr_step, "answers": answers.answers }),
+            )?;
+        }
+        Ok(())
+    }
+
+    async fn drive<R, F>(
+        &self,
+        ctx: &Context,
+        msg: &mut UpdatAbleMessage,
+        finish: F,
+        mut curr_step: usize,
+        mut answers: WizardAnswers,
+    ) -> Result<R, WizardError>
+    where
+        F: Fn(&WizardAnswers) -> Result<R, String> + Send + Sync,
+        R: Send,
+    {
+        let mut error: Option<String> = None;
+
+        if self.steps.is_empty() {
+            return finish(&answers).map_err(WizardError::Invalid);
+        }
+
+        let mut shown = self.render(curr_step, &answers, error.as_deref());
+        self.save(msg.msg(), curr_step, &answers)?;
+        msg.update(ctx, shown.0.clone(), shown.1.clone()).await?;
+
+        let mut interactions = super::component_interactions(ctx, msg.msg()).await;
+        let mut modals = super::modal_submissions(ctx, msg.msg()).await;
+        let cancelled = cancelled(self.cancel_token.as_ref());
+        tokio::pin!(cancelled);
+
+        loop {
+            let step = &self.steps[curr_step];
+            // Some(answer) if the current step should be answered and left
+            let mut advance: Option<WizardAnswer> = None;
+
+            // using select instead of collector timeout to reset
+            // timeout after button click
+            select! {
+                react = interactions.next() => {
+                    // Should always be some
+                    let react = react.unwrap();
+
+                    if react.data.custom_id == SELECT_ID {
+                        let selected = react
+                            .data
+                            .values
+                            .iter()
+                            .filter_map(|v| v.parse::<usize>().ok())
+                            .collect();
+                        answers
+                            .answers
+                            .insert(step.key.clone(), WizardAnswer::Selected(selected));
+                        error = None;
+                    } else if let Some(c) = react.data.custom_id.strip_prefix(CHOICE_ID) {
+                        if let Ok(c) = c.parse::<usize>() {
+                            advance = Some(WizardAnswer::Choice(c));
+                        }
+                    } else if react.data.custom_id == INPUT_ID {
+                        if let WizardStep::Input { label, style } = &step.kind {
+                            let current = answers.input(&step.key).unwrap_or("").to_string();
+                            // The modal is the response, the message is
+                            // updated once it got submitted
+                            react
+                                .create_interaction_response(ctx, |r| {
+                                    r.kind(InteractionResponseType::Modal)
+                                        .interaction_response_data(|d| {
+                                            d.custom_id(MODAL_ID).title(&step.title).components(|c| {
+                                                c.create_action_row(|ar| {
+                                                    ar.create_input_text(|t| {
+                                                        t.custom_id(INPUT_ID)
+                                                            .label(label)
+                                                            .style(*style)
+                                                            .value(current)
+                                                            .required(true)
+                                                    })
+                                                })
+                                            })
+                                        })
+                                })
+                                .await?;
+                        }
+                        continue;
+                    } else {
+                        match react.parse_button() {
+                            Ok(Button::Abort) => {
+                                react.defer(ctx).await?;
+                                return Err(WizardError::Aborted);
+                            }
+                            Ok(Button::Previous) => {
+                                curr_step = curr_step.saturating_sub(1);
+                                error = None;
+                            }
+                            Ok(Button::Next) => {
+                                advance = step.answer(&answers).map(Cow::into_owned)
+                            }
+                            Ok(Button::Confirm) => advance = Some(WizardAnswer::Confirmed),
+                            _ => (),
+                        }
+                    }
+
+                    if let Some(answer) = advance.take() {
+                        match self.answer(curr_step, &mut answers, answer, &finish) {
+                            Ok(Some(r)) => {
+                                react.defer(ctx).await?;
+                                return Ok(r);
+                            }
+                            Ok(None) => {
+                                curr_step += 1;
+                                error = None;
+                            }
+                            Err(e) => error = Some(e),
+                        }
+                    }
+
+                    shown = self.render(curr_step, &answers, error.as_deref());
+                    react.defer(ctx).await?;
+                    self.save(msg.msg(), curr_step, &answers)?;
+                    msg.update(ctx, shown.0.clone(), shown.1.clone()).await?;
+                },
+                modal = modals.next() => {
+                    // Should always be some
+                    let modal = modal.unwrap();
+
+                    if modal.data.custom_id != MODAL_ID {
+                        continue;
+                    }
+                    let value = modal
+                        .data
+                        .components
+                        .iter()
+                        .flat_map(|ar| &ar.components)
+                        .find_map(|c| match c {
+                            ActionRowComponent::InputText(t) if t.custom_id == INPUT_ID => {
+                                Some(t.value.clone())
+                            }
+                            _ => None,
+                        })
+                        .unwrap_or_default();
+
+                    match self.answer(curr_step, &mut answers, WizardAnswer::Input(value), &finish) {
+                        Ok(Some(r)) => {
+                            modal.defer(ctx).await?;
+                            return Ok(r);
+                        }
+                        Ok(None) => {
+                            curr_step += 1;
+                            error = None;
+                        }
+                        Err(e) => error = Some(e),
+                    }
+
+                    shown = self.render(curr_step, &answers, error.as_deref());
+                    modal.defer(ctx).await?;
+                    self.save(msg.msg(), curr_step, &answers)?;
+                    msg.update(ctx, shown.0.clone(), shown.1.clone()).await?;
+                },
+                _ = sleep(self.timeout) => {
+                    msg.expire(ctx, &self.expiry, shown.0, shown.1).await?;
+                    return Err(WizardError::TimedOut);
+                },
+                _ = &mut cancelled => {
+                    msg.cancel(ctx).await?;
+                    return Err(WizardError::Cancelled);
+                },
+            }
+        }
+    }
+
+    // Stores the answer if valid. Returns the result if it was the last step.
+    fn answer<R, F>(
+        &self,
+        curr_step: usize,
+        answers: &mut WizardAnswers,
+        answer: WizardAnswer,
+        finish: &F,
+    ) -> Result<Option<R>, String>
+    where
+        F: Fn(&WizardAnswers) -> Result<R, String>,
+    {
+        let step = &self.steps[curr_step];
+        if let Some(validate) = &step.validate {
+            validate(&answer)?;
+        }
+        answers.answers.insert(step.key.clone(), answer);
+
+        if curr_step + 1 == self.steps.len() {
+            finish(answers).map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn render(
+        &self,
+        curr_step: usize,
+        answers: &WizardAnswers,
+        error: Option<&str>,
+    ) -> (Vec<CreateEmbed>, Vec<CreateActionRow>) {
+        let step = &self.steps[curr_step];
+        let answer = step.answer(answers);
+
+        let mut emb = self.base_embed.clone();
+        let progress = (0..self.steps.len())
+            .map(|i| if i <= curr_step { "▰" } else { "▱" })
+            .collect::<String>();
+        emb.field(
+            format!("Step {}/{}", curr_step + 1, self.steps.len()),
+            format!("{}\n**{}**", progress, step.title),
+            false,
+        );
+
+        let mut ar = Vec::new();
+        match &step.kind {
+            WizardStep::Select { options, min, max } => {
+                let selected = answers.selected(&step.key).unwrap_or(&[]);
+                // Only the first 25 options are shown
+                let shown = options.len().min(25);
+                let mut sar = CreateActionRow::default();
+                sar.create_select_menu(|m| {
+                    m.custom_id(SELECT_ID)
+                        .min_values((*min).min(shown) as u64)
+                        .max_values((*max).min(shown) as u64)
+                        .options(|o| {
+                            for (i, opt) in options.iter().enumerate().take(25) {
+                                o.create_option(|op| {
+                                    op.label(opt)
+                                        .value(i)
+                                        .default_selection(selected.contains(&i))
+                                });
+                            }
+                            o
+                        })
+                });
+                ar.push(sar);
+            }
+            WizardStep::Choice { options } => {
+                let chosen = answers.choice(&step.key);
+                for (r, row) in options.chunks(5).enumerate().take(4) {
+                    let mut car = CreateActionRow::default();
+                    for (i, opt) in row.iter().enumerate() {
+                        let i = r * 5 + i;
+                        let mut b = CreateButton::default();
+                        b.label(opt).custom_id(format!("{}{}", CHOICE_ID, i)).style(
+                            if chosen == Some(i) {
+                                ButtonStyle::Success
+                            } else {
+                                ButtonStyle::Primary
+                            },
+                        );
+                        car.add_button(b);
+                    }
+                    ar.push(car);
+                }
+            }
+            WizardStep::Input { .. } => {
+                if let Some(text) = answers.input(&step.key) {
+                    emb.field("Current input", text, false);
+                }
+                let mut iar = CreateActionRow::default();
+                iar.create_button(|b| {
+                    b.label("enter")
+                        .emoji('✏')
+                        .custom_id(INPUT_ID)
+                        .style(ButtonStyle::Primary)
+                });
+                ar.push(iar);
+            }
+            WizardStep::Confirm { text } => {
+                emb.description(text);
+            }
+        }
+
+        if let Some(error) = error {
+            emb.field("**ERROR**", error, false);
+        }
+        if self.show_expiry {
+            let now = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default();
+            expiry_field(&mut emb, now + self.timeout);
+        }
+
+        let mut nar = CreateActionRow::default();
+        if curr_step > 0 {
+            nar.prev_button();
+        }
+        nar.abort_button();
+        match step.kind {
+            WizardStep::Confirm { .. } => {
+                nar.confirm_button();
+            }
+            // Choices already advance on click. Next is for steps
+            // that have been answered before or can be left empty.
+            _ => {
+                let mut next = Button::Next.create();
+                next.disabled(answer.is_none());
+                nar.add_button(next);
+            }
+        }
+        ar.push(nar);
+
+        (vec![emb], ar)
+    }
+}
@@ -2,7 +2,7 @@ use std::str::FromStr;
 
 use serenity::{
     builder::CreateButton,
-    model::{channel::ReactionType, interactions::message_component::ButtonStyle},
+    model::{application::component::ButtonStyle, channel::ReactionType},
 };
 
 pub(crate) struct ButtonInfo {
@@ -12,21 +12,28 @@ pub(crate) struct ButtonInfo {
 }
 
 impl ButtonInfo {
+    pub(crate) fn emoji(&self) -> ReactionType {
+        ReactionType::try_from(self.emoji).unwrap()
+    }
+
     pub(crate) fn create(&self) -> CreateButton {
         let mut button = CreateButton::default();
         button.label(self.text);
         button.custom_id(self.id);
         button.style(ButtonStyle::Primary);
-        button.emoji(ReactionType::try_from(self.emoji).unwrap());
+        button.emoji(self.emoji());
         button
     }
 }
 
 macro_rules! button {
     ($name:ident,$text:literal,$emoji:literal) => {
+        button!($name, $text, $text, $emoji);
+    };
+    ($name:ident,$text:literal,$id:literal,$emoji:literal) => {
         pub(crate) const $name: ButtonInfo = ButtonInfo {
             text: $text,
-            id: concat!("_tools_button_", $text),
+            id: concat!("_tools_button_", $id),
             emoji: $emoji,
         };
     };
@@ -36,12 +43,28 @@ button!(CONFIRM_BUTTON, "confirm", "✅");
 button!(ABORT_BUTTON, "abort", "❌");
 button!(NEXT_BUTTON, "next", "⏩");
 button!(PREV_BUTTON, "previous", "⏪");
+button!(SELECT_PAGE_BUTTON, "select page", "select_page", "📄");
+button!(SELECT_ALL_BUTTON, "select all", "select_all", "📚");
+button!(CLEAR_ALL_BUTTON, "clear all", "clear_all", "🧹");
+button!(INVERT_BUTTON, "invert", "🔄");
+button!(UNDO_BUTTON, "undo", "↩️");
+button!(DECREASE_BUTTON, "less", "➖");
+button!(INCREASE_BUTTON, "more", "➕");
+button!(LEAVE_BUTTON, "leave", "🚪");
 
 pub enum Button {
     Confirm,
     Abort,
     Next,
     Previous,
+    SelectPage,
+    SelectAll,
+    ClearAll,
+    Invert,
+    Undo,
+    Decrease,
+    Increase,
+    Leave,
 }
 
 impl Button {
@@ -67,15 +90,72 @@ impl Button {
                 b.style(ButtonStyle::Primary);
                 b
             }
+            Self::SelectPage => {
+                let mut b = SELECT_PAGE_BUTTON.create();
+                b.style(ButtonStyle::Secondary);
+                b
+            }
+            Self::SelectAll => {
+                let mut b = SELECT_ALL_BUTTON.create();
+                b.style(ButtonStyle::Secondary);
+                b
+            }
+            Self::ClearAll => {
+                let mut b = CLEAR_ALL_BUTTON.create();
+                b.style(ButtonStyle::Secondary);
+                b
+            }
+            Self::Invert => {
+                let mut b = INVERT_BUTTON.create();
+                b.style(ButtonStyle::Secondary);
+                b
+            }
+            Self::Undo => {
+                let mut b = UNDO_BUTTON.create();
+                b.style(ButtonStyle::Secondary);
+                b
+            }
+            Self::Decrease => {
+                let mut b = DECREASE_BUTTON.create();
+                b.style(ButtonStyle::Secondary);
+                b
+            }
+            Self::Increase => {
+                let mut b = INCREASE_BUTTON.create();
+                b.style(ButtonStyle::Secondary);
+                b
+            }
+            Self::Leave => {
+                let mut b = LEAVE_BUTTON.create();
+                b.style(ButtonStyle::Danger);
+                b
+            }
         }
     }
 
     pub fn id(&self) -> &'static str {
+        self.info().id
+    }
+
+    /// The emoji of the button, also used for reactions
+    pub fn emoji(&self) -> ReactionType {
+        self.info().emoji()
+    }
+
+    fn info(&self) -> &'static ButtonInfo {
         match self {
-            Self::Confirm => CONFIRM_BUTTON.id,
-            Self::Abort => ABORT_BUTTON.id,
-            Self::Next => NEXT_BUTTON.id,
-            Self::Previous => PREV_BUTTON.id,
+            Self::Confirm => &CONFIRM_BUTTON,
+            Self::Abort => &ABORT_BUTTON,
+            Self::Next => &NEXT_BUTTON,
+            Self::Previous => &PREV_BUTTON,
+            Self::SelectPage => &SELECT_PAGE_BUTTON,
+            Self::SelectAll => &SELECT_ALL_BUTTON,
+            Self::ClearAll => &CLEAR_ALL_BUTTON,
+            Self::Invert => &INVERT_BUTTON,
+            Self::Undo => &UNDO_BUTTON,
+            Self::Decrease => &DECREASE_BUTTON,
+            Self::Increase => &INCREASE_BUTTON,
+            Self::Leave => &LEAVE_BUTTON,
         }
     }
 }
@@ -85,7 +165,7 @@ pub struct ButtonParseError(String);
 
 impl std::fmt::Display for ButtonParseError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "Unknown custom button id: {}", self)
+        write!(f, "Unknown custom button id: {}", self.0)
     }
 }
 
@@ -101,6 +181,14 @@ impl FromStr for Button {
             "_tools_button_abort" => Ok(Button::Abort),
             "_tools_button_next" => Ok(Button::Next),
             "_tools_button_previous" => Ok(Button::Previous),
+            "_tools_button_select_page" => Ok(Button::SelectPage),
+            "_tools_button_select_all" => Ok(Button::SelectAll),
+            "_tools_button_clear_all" => Ok(Button::ClearAll),
+            "_tools_button_invert" => Ok(Button::Invert),
+            "_tools_button_undo" => Ok(Button::Undo),
+            "_tools_button_less" => Ok(Button::Decrease),
+            "_tools_button_more" => Ok(Button::Increase),
+            "_tools_button_leave" => Ok(Button::Leave),
             _ => Err(ButtonParseError(s.to_string())),
         }
     }
@@ -3,11 +3,11 @@ use serenity::{
     builder::CreateEmbed,
     client::Context,
     model::{
-        channel::Message,
-        interactions::{
-            application_command::ApplicationCommandInteraction,
-            InteractionApplicationCommandCallbackDataFlags, InteractionResponseType,
+        application::interaction::{
+            application_command::ApplicationCommandInteraction, InteractionResponseType,
+            MessageFlags,
         },
+        channel::Message,
     },
     Result,
 };
@@ -98,7 +98,7 @@ impl ApplicationCommandInteractionExt for ApplicationCommandInteraction {
             r.kind(InteractionResponseType::ChannelMessageWithSource);
             r.interaction_response_data(|d| {
                 if ephemeral {
-                    d.flags(InteractionApplicationCommandCallbackDataFlags::EPHEMERAL);
+                    d.flags(MessageFlags::EPHEMERAL);
                 }
                 d.add_embed(CreateEmbed::info_box(text))
             })
@@ -116,7 +116,7 @@ impl ApplicationCommandInteractionExt for ApplicationCommandInteraction {
             r.kind(InteractionResponseType::ChannelMessageWithSource);
             r.interaction_response_data(|d| {
                 if ephemeral {
-                    d.flags(InteractionApplicationCommandCallbackDataFlags::EPHEMERAL);
+                    d.flags(MessageFlags::EPHEMERAL);
                 }
                 d.add_embed(CreateEmbed::error_box(text))
             })
@@ -134,7 +134,7 @@ impl ApplicationCommandInteractionExt for ApplicationCommandInteraction {
             r.kind(InteractionResponseType::ChannelMessageWithSource);
             r.interaction_response_data(|d| {
                 if ephemeral {
-                    d.flags(InteractionApplicationCommandCallbackDataFlags::EPHEMERAL);
+                    d.flags(MessageFlags::EPHEMERAL);
                 }
                 d.add_embed(CreateEmbed::success_box(text))
             })
@@ -188,7 +188,7 @@ impl ApplicationCommandInteractionExt for ApplicationCommandInteraction {
     ) -> Result<Message> {
         self.create_followup_message(ctx, |m| {
             if ephemeral {
-                m.flags(InteractionApplicationCommandCallbackDataFlags::EPHEMERAL);
+                m.flags(MessageFlags::EPHEMERAL);
             }
             m.add_embed(CreateEmbed::info_box(text))
         })
@@ -203,7 +203,7 @@ impl ApplicationCommandInteractionExt for ApplicationCommandInteraction {
     ) -> Result<Message> {
         self.create_followup_message(ctx, |m| {
             if ephemeral {
-                m.flags(InteractionApplicationCommandCallbackDataFlags::EPHEMERAL);
+                m.flags(MessageFlags::EPHEMERAL);
             }
             m.add_embed(CreateEmbed::error_box(text))
         })
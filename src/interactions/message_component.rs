@@ -1,13 +1,12 @@
 use serenity::{
     async_trait,
-    builder::CreateEmbed,
+    builder::{CreateActionRow, CreateEmbed},
     client::Context,
     model::{
-        channel::Message,
-        interactions::{
-            message_component::MessageComponentInteraction,
-            InteractionApplicationCommandCallbackDataFlags, InteractionResponseType,
+        application::interaction::{
+            message_component::MessageComponentInteraction, InteractionResponseType, MessageFlags,
         },
+        channel::Message,
     },
     Result,
 };
@@ -26,6 +25,14 @@ pub trait MessageComponentInteractionExt {
 
     async fn deferred_reply(&self, ctx: &Context) -> Result<()>;
 
+    /// Answers the click by editing the message it belongs to
+    async fn update_message(
+        &self,
+        ctx: &Context,
+        embeds: Vec<CreateEmbed>,
+        rows: Vec<CreateActionRow>,
+    ) -> Result<()>;
+
     async fn create_quick_info<T: ToString + Send>(
         &self,
         ctx: &Context,
@@ -93,6 +100,22 @@ impl MessageComponentInteractionExt for MessageComponentInteraction {
         .await
     }
 
+    async fn update_message(
+        &self,
+        ctx: &Context,
+        embeds: Vec<CreateEmbed>,
+        rows: Vec<CreateActionRow>,
+    ) -> Result<()> {
+        self.create_interaction_response(ctx, |r| {
+            r.kind(InteractionResponseType::UpdateMessage);
+            r.interaction_response_data(|d| {
+                d.set_embeds(embeds);
+                d.components(|c| c.set_action_rows(rows))
+            })
+        })
+        .await
+    }
+
     async fn create_quick_info<T: ToString + Send>(
         &self,
         ctx: &Context,
@@ -103,7 +126,7 @@ impl MessageComponentInteractionExt for MessageComponentInteraction {
             r.kind(InteractionResponseType::ChannelMessageWithSource);
             r.interaction_response_data(|d| {
                 if ephemeral {
-                    d.flags(InteractionApplicationCommandCallbackDataFlags::EPHEMERAL);
+                    d.flags(MessageFlags::EPHEMERAL);
                 }
                 d.add_embed(CreateEmbed::info_box(text))
             })
@@ -121,7 +144,7 @@ impl MessageComponentInteractionExt for MessageComponentInteraction {
             r.kind(InteractionResponseType::ChannelMessageWithSource);
             r.interaction_response_data(|d| {
                 if ephemeral {
-                    d.flags(InteractionApplicationCommandCallbackDataFlags::EPHEMERAL);
+                    d.flags(MessageFlags::EPHEMERAL);
                 }
                 d.add_embed(CreateEmbed::error_box(text))
             })
@@ -161,7 +184,7 @@ impl MessageComponentInteractionExt for MessageComponentInteraction {
     ) -> Result<Message> {
         self.create_followup_message(ctx, |m| {
             if ephemeral {
-                m.flags(InteractionApplicationCommandCallbackDataFlags::EPHEMERAL);
+                m.flags(MessageFlags::EPHEMERAL);
             }
             m.add_embed(CreateEmbed::info_box(text))
         })
@@ -176,7 +199,7 @@ impl MessageComponentInteractionExt for MessageComponentInteraction {
     ) -> Result<Message> {
         self.create_followup_message(ctx, |m| {
             if ephemeral {
-                m.flags(InteractionApplicationCommandCallbackDataFlags::EPHEMERAL);
+                m.flags(MessageFlags::EPHEMERAL);
             }
             m.add_embed(CreateEmbed::error_box(text))
         })
@@ -2,6 +2,8 @@ pub mod builder;
 pub mod collectors;
 pub mod components;
 pub mod interactions;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
 
 #[cfg(test)]
 mod tests {
@@ -0,0 +1,637 @@
+//! Offline stand-in for Discord to test interaction flows.
+//!
+//! [`MockDiscord`] runs a local HTTP server that answers the Discord API
+//! calls made through its [`Context`] and records them. Component
+//! interactions can be injected and are picked up by the collectors of
+//! this crate instead of the gateway.
+
+use std::{
+    collections::HashMap,
+    convert::Infallible,
+    net::SocketAddr,
+    path::Path,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+
+use hyper::{
+    service::{make_service_fn, service_fn},
+    Body, Method, Request, Response, Server, StatusCode,
+};
+use serde_json::{json, Value};
+use serenity::{
+    builder::{CreateActionRow, CreateButton, CreateComponents, CreateEmbed, CreateSelectMenu},
+    client::{bridge::gateway::ShardMessenger, Context},
+    collector::ReactionAction,
+    futures::channel::{
+        mpsc::{unbounded, UnboundedReceiver, UnboundedSender},
+        oneshot,
+    },
+    gateway::InterMessage,
+    http::HttpBuilder,
+    model::{
+        application::interaction::{
+            message_component::MessageComponentInteraction, modal::ModalSubmitInteraction,
+        },
+        channel::{Attachment, Message},
+    },
+    prelude::{RwLock, TypeMap, TypeMapKey},
+};
+use tokio::time::sleep;
+
+/// Interactions and messages injected into the collectors, kept in the
+/// `TypeMap` of the context. Keyed by the message the interactions are on
+/// or the channel the messages are sent in.
+pub(crate) struct Injected<T> {
+    // Items that arrived before a collector was listening get queued
+    queues: Arc<Mutex<HashMap<u64, Queue<T>>>>,
+}
+
+pub(crate) type InjectedInteractions = Injected<Arc<MessageComponentInteraction>>;
+pub(crate) type InjectedMessages = Injected<Arc<Message>>;
+pub(crate) type InjectedReactions = Injected<Arc<ReactionAction>>;
+pub(crate) type InjectedModals = Injected<Arc<ModalSubmitInteraction>>;
+
+impl<T> Clone for Injected<T> {
+    fn clone(&self) -> Self {
+        Self {
+            queues: self.queues.clone(),
+        }
+    }
+}
+
+impl<T> Default for Injected<T> {
+    fn default() -> Self {
+        Self {
+            queues: Default::default(),
+        }
+    }
+}
+
+struct Queue<T> {
+    pending: Vec<T>,
+    listener: Option<UnboundedSender<T>>,
+}
+
+impl<T> Default for Queue<T> {
+    fn default() -> Self {
+        Self {
+            pending: Vec::new(),
+            listener: None,
+        }
+    }
+}
+
+impl<T: Send + Sync + 'static> TypeMapKey for Injected<T> {
+    type Value = Injected<T>;
+}
+
+impl<T> Injected<T> {
+    pub(crate) fn listen(&self, key: u64) -> UnboundedReceiver<T> {
+        let (tx, rx) = unbounded();
+        let mut queues = self.queues.lock().unwrap();
+        let queue = queues.entry(key).or_default();
+        for item in queue.pending.drain(..) {
+            let _ = tx.unbounded_send(item);
+        }
+        queue.listener = Some(tx);
+        rx
+    }
+
+    fn send(&self, key: u64, item: T) {
+        let mut queues = self.queues.lock().unwrap();
+        let queue = queues.entry(key).or_default();
+        match &queue.listener {
+            Some(tx) if !tx.is_closed() => {
+                let _ = tx.unbounded_send(item);
+            }
+            _ => queue.pending.push(item),
+        }
+    }
+}
+
+/// A request the mock server received
+#[derive(Clone, Debug)]
+pub struct RecordedRequest {
+    pub method: Method,
+    /// Path without the api prefix, e.g. `/channels/1/messages/2`
+    pub path: String,
+    /// The JSON body or `Value::Null`
+    pub body: Value,
+}
+
+// Requests the server answers with an error, and its Discord error code
+type FailingRequests = Arc<Mutex<Option<(u64, Box<dyn Fn(&RecordedRequest) -> bool + Send>)>>>;
+
+/// Local stand-in for the Discord HTTP API. Stops when dropped.
+pub struct MockDiscord {
+    addr: SocketAddr,
+    requests: Arc<Mutex<Vec<RecordedRequest>>>,
+    failing: FailingRequests,
+    injected: InjectedInteractions,
+    messages: InjectedMessages,
+    reactions: InjectedReactions,
+    modals: InjectedModals,
+    #[cfg(feature = "cache")]
+    cache: Arc<serenity::cache::Cache>,
+    // Keeps the channel of the fake shard open
+    _shard: UnboundedReceiver<InterMessage>,
+    shard_tx: UnboundedSender<InterMessage>,
+    shutdown: Option<oneshot::Sender<()>>,
+}
+
+impl MockDiscord {
+    pub async fn start() -> Self {
+        let requests: Arc<Mutex<Vec<RecordedRequest>>> = Default::default();
+        let failing: FailingRequests = Default::default();
+        let ids = Arc::new(AtomicU64::new(1_000));
+
+        let recorded = requests.clone();
+        let fail = failing.clone();
+        let make_svc = make_service_fn(move |_| {
+            let recorded = recorded.clone();
+            let fail = fail.clone();
+            let ids = ids.clone();
+            async move {
+                Ok::<_, Infallible>(service_fn(move |req| {
+                    respond(req, recorded.clone(), fail.clone(), ids.clone())
+                }))
+            }
+        });
+
+        let server = Server::bind(&([127, 0, 0, 1], 0).into()).serve(make_svc);
+        let addr = server.local_addr();
+        let (shutdown, rx) = oneshot::channel::<()>();
+        tokio::spawn(server.with_graceful_shutdown(async {
+            let _ = rx.await;
+        }));
+
+        let (shard_tx, shard) = unbounded();
+
+        Self {
+            addr,
+            requests,
+            failing,
+            injected: Default::default(),
+            messages: Default::default(),
+            reactions: Default::default(),
+            modals: Default::default(),
+            #[cfg(feature = "cache")]
+            cache: Arc::new(bot_cache()),
+            _shard: shard,
+            shard_tx,
+            shutdown: Some(shutdown),
+        }
+    }
+
+    /// A context whose http client talks to this server and whose
+    /// collectors receive the injected interactions.
+    pub fn context(&self) -> Context {
+        let http = HttpBuilder::new("mock")
+            .application_id(1)
+            .ratelimiter_disabled(true)
+            .proxy(format!("http://{}/", self.addr))
+            .expect("Valid proxy url")
+            .build();
+
+        let mut data = TypeMap::new();
+        data.insert::<InjectedInteractions>(self.injected.clone());
+        data.insert::<InjectedMessages>(self.messages.clone());
+        data.insert::<InjectedReactions>(self.reactions.clone());
+        data.insert::<InjectedModals>(self.modals.clone());
+
+        Context {
+            data: Arc::new(RwLock::new(data)),
+            #[cfg(feature = "cache")]
+            cache: self.cache.clone(),
+            shard: ShardMessenger::new(self.shard_tx.clone()),
+            shard_id: 0,
+            http: Arc::new(http),
+        }
+    }
+
+    /// The cache of the contexts, e.g. to add guilds with
+    /// [`CacheUpdate`](serenity::cache::CacheUpdate) events
+    #[cfg(feature = "cache")]
+    pub fn cache(&self) -> &Arc<serenity::cache::Cache> {
+        &self.cache
+    }
+
+    /// Delivers the interaction to the collectors on its message
+    pub fn inject(&self, interaction: MessageComponentInteraction) {
+        self.injected
+            .send(interaction.message.id.0, Arc::new(interaction));
+    }
+
+    /// Delivers the reaction to the collectors on its message
+    pub fn inject_reaction(&self, reaction: ReactionAction) {
+        let message = reaction.as_inner_ref().message_id;
+        self.reactions.send(message.0, Arc::new(reaction));
+    }
+
+    /// Delivers the modal submission to the collectors on the message
+    /// whose component opened the modal
+    pub fn inject_modal(&self, interaction: ModalSubmitInteraction) {
+        let message = interaction
+            .message
+            .as_ref()
+            .expect("Modal opened from a message")
+            .id;
+        self.modals.send(message.0, Arc::new(interaction));
+    }
+
+    /// A file uploaded to this server, downloading it yields its name
+    pub fn attachment(&self, id: u64, filename: &str, content_type: &str, size: u64) -> Attachment {
+        let url = format!("http://{}/attachments/{}/{}", self.addr, id, filename);
+        serde_json::from_value(json!({
+            "id": id.to_string(),
+            "filename": filename,
+            "content_type": content_type,
+            "size": size,
+            "url": url,
+            "proxy_url": url,
+        }))
+        .expect("Valid attachment")
+    }
+
+    /// Delivers the message to the collectors in its channel
+    pub fn inject_message(&self, message: Message) {
+        self.messages.send(message.channel_id.0, Arc::new(message));
+    }
+
+    pub fn requests(&self) -> Vec<RecordedRequest> {
+        self.requests.lock().unwrap().clone()
+    }
+
+    pub fn clear_requests(&self) {
+        self.requests.lock().unwrap().clear();
+    }
+
+    /// Answers the matching requests with a 400 error, they still get
+    /// recorded
+    pub fn fail_requests<F>(&self, fail: F)
+    where
+        F: Fn(&RecordedRequest) -> bool + Send + 'static,
+    {
+        self.fail_requests_with(50035, fail);
+    }
+
+    /// Like [`fail_requests`](Self::fail_requests) with the Discord error
+    /// code, e.g. 40060 for already acknowledged interactions
+    pub fn fail_requests_with<F>(&self, code: u64, fail: F)
+    where
+        F: Fn(&RecordedRequest) -> bool + Send + 'static,
+    {
+        *self.failing.lock().unwrap() = Some((code, Box::new(fail)));
+    }
+
+    /// Waits until at least `count` requests have been received.
+    /// Panics after 5 seconds.
+    pub async fn wait_for_requests(&self, count: usize) -> Vec<RecordedRequest> {
+        for _ in 0..500 {
+            let requests = self.requests();
+            if requests.len() >= count {
+                return requests;
+            }
+            sleep(Duration::from_millis(10)).await;
+        }
+        panic!("Expected {} requests, got {:?}", count, self.requests());
+    }
+}
+
+impl Drop for MockDiscord {
+    fn drop(&mut self) {
+        if let Some(shutdown) = self.shutdown.take() {
+            let _ = shutdown.send(());
+        }
+    }
+}
+
+async fn respond(
+    req: Request<Body>,
+    recorded: Arc<Mutex<Vec<RecordedRequest>>>,
+    failing: FailingRequests,
+    ids: Arc<AtomicU64>,
+) -> Result<Response<Body>, Infallible> {
+    let method = req.method().clone();
+    let path = req.uri().path().trim_start_matches("/api/v10").to_string();
+    let bytes = hyper::body::to_bytes(req.into_body())
+        .await
+        .unwrap_or_default();
+    let body = serde_json::from_slice(&bytes)
+        .ok()
+        .or_else(|| payload_json(&bytes))
+        .unwrap_or(Value::Null);
+
+    let request = RecordedRequest {
+        method: method.clone(),
+        path: path.clone(),
+        body,
+    };
+    let fail = failing
+        .lock()
+        .unwrap()
+        .as_ref()
+        .filter(|(_, f)| f(&request))
+        .map(|(code, _)| *code);
+    recorded.lock().unwrap().push(request);
+    if let Some(code) = fail {
+        let body = json!({"code": code, "message": "Request failed"});
+        return Ok(Response::builder()
+            .status(StatusCode::BAD_REQUEST)
+            .header("content-type", "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap());
+    }
+
+    // Attachments contain their file name
+    if let Some(file) = path.strip_prefix("/attachments/") {
+        let name = file.rsplit('/').next().unwrap_or_default().to_string();
+        return Ok(Response::new(Body::from(name)));
+    }
+
+    let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+    let id = |s: &str| s.parse::<u64>().unwrap_or(1);
+    let message = match (&method, segments.as_slice()) {
+        (&Method::DELETE, _) | (_, ["interactions", _, _, "callback"]) => None,
+        (_, ["channels", _, "messages", _, "reactions", ..]) => None,
+        (_, ["channels", c, "messages", m]) => Some(message_json(id(c), id(m))),
+        (&Method::POST, ["channels", c, "messages"]) => {
+            Some(message_json(id(c), ids.fetch_add(1, Ordering::Relaxed)))
+        }
+        (_, ["webhooks", _, _, "messages", "@original"]) => Some(message_json(1, 1)),
+        (_, ["webhooks", _, _, "messages", m]) => Some(message_json(1, id(m))),
+        (&Method::POST, ["webhooks", _, _]) => {
+            Some(message_json(1, ids.fetch_add(1, Ordering::Relaxed)))
+        }
+        _ => {
+            let body = json!({"code": 0, "message": "404: Not Found"});
+            return Ok(Response::builder()
+                .status(StatusCode::NOT_FOUND)
+                .header("content-type", "application/json")
+                .body(Body::from(body.to_string()))
+                .unwrap());
+        }
+    };
+
+    Ok(match message {
+        Some(message) => Response::builder()
+            .header("content-type", "application/json")
+            .body(Body::from(message.to_string()))
+            .unwrap(),
+        None => Response::builder()
+            .status(StatusCode::NO_CONTENT)
+            .body(Body::empty())
+            .unwrap(),
+    })
+}
+
+// Message edits are sent as multipart form with the JSON in a field
+fn payload_json(bytes: &[u8]) -> Option<Value> {
+    let form = std::str::from_utf8(bytes).ok()?;
+    let field = &form[form.find("name=\"payload_json\"")?..];
+    let value = &field[field.find("\r\n\r\n")? + 4..];
+    serde_json::from_str(&value[..value.find("\r\n--")?]).ok()
+}
+
+fn user_json(id: u64) -> Value {
+    json!({
+        "id": id.to_string(),
+        "username": format!("user{}", id),
+        "discriminator": "0001",
+        "avatar": null,
+    })
+}
+
+fn message_json(channel_id: u64, message_id: u64) -> Value {
+    json!({
+        "id": message_id.to_string(),
+        "channel_id": channel_id.to_string(),
+        "author": user_json(1),
+        "content": "",
+        "timestamp": "2022-01-01T00:00:00.000000+00:00",
+        "edited_timestamp": null,
+        "tts": false,
+        "mention_everyone": false,
+        "mentions": [],
+        "mention_roles": [],
+        "attachments": [],
+        "embeds": [],
+        "components": [],
+        "pinned": false,
+        "type": 0,
+    })
+}
+
+// Messages of the bot are only edited if the cache knows it is the author
+#[cfg(feature = "cache")]
+fn bot_cache() -> serenity::cache::Cache {
+    let mut user = user_json(1);
+    user["bot"] = json!(true);
+    user["mfa_enabled"] = json!(false);
+    user["verified"] = json!(true);
+    let ready = json!({
+        "application": { "id": "1", "flags": 0 },
+        "guilds": [],
+        "session_id": "mock",
+        "user": user,
+        "v": 10,
+    });
+    let mut ready: serenity::model::event::ReadyEvent =
+        serde_json::from_value(ready).expect("Valid ready event");
+    let cache = serenity::cache::Cache::new();
+    cache.update(&mut ready);
+    cache
+}
+
+/// A message sent by the bot
+pub fn message(channel_id: u64, message_id: u64) -> Message {
+    serde_json::from_value(message_json(channel_id, message_id)).expect("Valid message")
+}
+
+/// A message sent by the user
+pub fn user_message(channel_id: u64, message_id: u64, user_id: u64, content: &str) -> Message {
+    let mut message = message_json(channel_id, message_id);
+    message["author"] = user_json(user_id);
+    message["content"] = json!(content);
+    serde_json::from_value(message).expect("Valid message")
+}
+
+/// The user adding or removing the unicode emoji on the message
+pub fn reaction(message: &Message, user_id: u64, emoji: &str, added: bool) -> ReactionAction {
+    let reaction = serde_json::from_value(json!({
+        "user_id": user_id.to_string(),
+        "channel_id": message.channel_id.to_string(),
+        "message_id": message.id.to_string(),
+        "emoji": { "id": null, "name": emoji },
+    }))
+    .expect("Valid reaction");
+    match added {
+        true => ReactionAction::Added(Arc::new(reaction)),
+        false => ReactionAction::Removed(Arc::new(reaction)),
+    }
+}
+
+/// A click by the user on the component with `custom_id` on the message.
+/// `values` are the chosen options of a select menu.
+pub fn component_interaction(
+    message: &Message,
+    user_id: u64,
+    custom_id: &str,
+    values: &[&str],
+) -> MessageComponentInteraction {
+    static IDS: AtomicU64 = AtomicU64::new(1);
+    let id = IDS.fetch_add(1, Ordering::Relaxed);
+    let component_type = if values.is_empty() { 2 } else { 3 };
+
+    serde_json::from_value(json!({
+        "id": id.to_string(),
+        "application_id": "1",
+        "type": 3,
+        "data": {
+            "custom_id": custom_id,
+            "component_type": component_type,
+            "values": values,
+        },
+        "channel_id": message.channel_id.to_string(),
+        "user": user_json(user_id),
+        "token": format!("token{}", id),
+        "version": 1,
+        "message": serde_json::to_value(message).expect("Serializable message"),
+        "locale": "en-US",
+    }))
+    .expect("Valid component interaction")
+}
+
+/// The user submitting the modal with `custom_id` that was opened from a
+/// component on the message. `inputs` are the custom IDs and values of
+/// its text inputs.
+pub fn modal_submit(
+    message: &Message,
+    user_id: u64,
+    custom_id: &str,
+    inputs: &[(&str, &str)],
+) -> ModalSubmitInteraction {
+    static IDS: AtomicU64 = AtomicU64::new(1);
+    let id = IDS.fetch_add(1, Ordering::Relaxed);
+    let rows: Vec<Value> = inputs
+        .iter()
+        .map(|(custom_id, value)| {
+            json!({
+                "type": 1,
+                "components": [{ "type": 4, "custom_id": custom_id, "value": value }],
+            })
+        })
+        .collect();
+
+    serde_json::from_value(json!({
+        "id": id.to_string(),
+        "application_id": "1",
+        "type": 5,
+        "data": { "custom_id": custom_id, "components": rows },
+        "channel_id": message.channel_id.to_string(),
+        "user": user_json(user_id),
+        "token": format!("modal{}", id),
+        "version": 1,
+        "message": serde_json::to_value(message).expect("Serializable message"),
+        "locale": "en-US",
+    }))
+    .expect("Valid modal submission")
+}
+
+/// Builders whose payload can be compared against a snapshot
+pub trait ToSnapshot {
+    /// The JSON that would be sent to Discord
+    fn to_snapshot(&self) -> Value;
+}
+
+macro_rules! to_snapshot {
+    ($($builder:ty),*) => {$(
+        impl ToSnapshot for $builder {
+            fn to_snapshot(&self) -> Value {
+                serde_json::to_value(&self.0).expect("Serializable builder")
+            }
+        }
+    )*};
+}
+
+to_snapshot!(CreateEmbed, CreateComponents);
+
+// Components get their type only when they are built into a row
+macro_rules! to_snapshot_built {
+    ($($builder:ty),*) => {$(
+        impl ToSnapshot for $builder {
+            fn to_snapshot(&self) -> Value {
+                self.clone().build()
+            }
+        }
+    )*};
+}
+
+to_snapshot_built!(CreateButton, CreateSelectMenu, CreateActionRow);
+
+impl ToSnapshot for Value {
+    fn to_snapshot(&self) -> Value {
+        self.clone()
+    }
+}
+
+impl<T: ToSnapshot> ToSnapshot for [T] {
+    fn to_snapshot(&self) -> Value {
+        Value::Array(self.iter().map(ToSnapshot::to_snapshot).collect())
+    }
+}
+
+impl<T: ToSnapshot> ToSnapshot for Vec<T> {
+    fn to_snapshot(&self) -> Value {
+        self.as_slice().to_snapshot()
+    }
+}
+
+/// Compares the payload with the JSON file at `path`. The file only gets
+/// written if the `UPDATE_SNAPSHOTS` environment variable is set, a missing
+/// snapshot fails.
+pub fn assert_snapshot<P: AsRef<Path>, T: ToSnapshot + ?Sized>(path: P, value: &T) {
+    let path = path.as_ref();
+    // Object keys are sorted, so the output is stable
+    let actual = serde_json::to_string_pretty(&value.to_snapshot()).unwrap() + "\n";
+
+    if std::env::var_os("UPDATE_SNAPSHOTS").is_some() {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).expect("Snapshot directory");
+        }
+        std::fs::write(path, actual).expect("Writable snapshot");
+        return;
+    }
+
+    if !path.exists() {
+        panic!(
+            "Snapshot {} missing, rerun with UPDATE_SNAPSHOTS=1",
+            path.display()
+        );
+    }
+    let expected = std::fs::read_to_string(path).expect("Readable snapshot");
+    assert!(
+        expected == actual,
+        "Snapshot {} does not match, rerun with UPDATE_SNAPSHOTS=1 to accept\nexpected:\n{}\nactual:\n{}",
+        path.display(),
+        expected,
+        actual
+    );
+}
+
+/// Compares the payload with `snapshots/<name>.json` in the calling crate.
+#[macro_export]
+macro_rules! assert_snapshot {
+    ($name:expr, $value:expr) => {
+        $crate::testing::assert_snapshot(
+            ::std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+                .join("snapshots")
+                .join(format!("{}.json", $name)),
+            &$value,
+        )
+    };
+}
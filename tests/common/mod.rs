@@ -0,0 +1,6 @@
+use serenity::model::channel::ReactionType;
+
+/// The same emoji for every item, labelled with the value
+pub fn button<T: ToString>(v: &T) -> (ReactionType, String) {
+    (ReactionType::Unicode("🔵".to_string()), v.to_string())
+}
@@ -0,0 +1,202 @@
+mod common;
+
+use std::time::Duration;
+
+use hyper::Method;
+use serenity::{
+    builder::CreateEmbed,
+    futures::StreamExt,
+    model::id::{ChannelId, UserId},
+};
+use serenity_tools::{
+    builder::CreateEmbedExt,
+    collectors::{
+        AttachmentFilter, MessageCollectorExt, PagedSelectorConfig, ReactionConfig,
+        ReplyCollectorExt, ReplyPromptConfig, UpdatAbleMessage,
+    },
+    components::Button,
+    interactions::MessageComponentInteractionExt,
+    testing::{component_interaction, message, reaction, user_message, MockDiscord},
+};
+
+use common::button;
+
+#[tokio::test]
+async fn spawned_interaction_response() {
+    let mock = MockDiscord::start().await;
+    let ctx = mock.context();
+    let mut msg = UpdatAbleMessage::interaction_response(&ctx, "token")
+        .await
+        .unwrap();
+    let original = msg.msg().clone();
+
+    let selector = tokio::spawn({
+        let ctx = ctx.clone();
+        async move {
+            msg.paged_selector(&ctx, PagedSelectorConfig::default(), &[1, 2], button)
+                .await
+                .map(|s| s.into_iter().copied().collect::<Vec<_>>())
+        }
+    });
+    mock.wait_for_requests(2).await;
+    mock.inject(component_interaction(
+        &original,
+        2,
+        "_tools_selector_0",
+        &[],
+    ));
+    mock.inject(component_interaction(
+        &original,
+        2,
+        Button::Confirm.id(),
+        &[],
+    ));
+
+    assert_eq!(selector.await.unwrap().unwrap(), vec![1]);
+    let requests = mock.requests();
+    assert_eq!(requests[0].method, Method::GET);
+    assert_eq!(requests[1].method, Method::PATCH);
+    assert_eq!(requests[1].path, "/webhooks/1/token/messages/@original");
+}
+
+#[tokio::test]
+async fn component_events() {
+    let mock = MockDiscord::start().await;
+    let ctx = mock.context();
+    let msg = message(10, 20);
+
+    mock.inject(component_interaction(&msg, 3, Button::Confirm.id(), &[]));
+    mock.inject(component_interaction(&msg, 2, "unknown", &[]));
+    mock.inject(component_interaction(&msg, 2, Button::Undo.id(), &[]));
+    let events = msg
+        .component_events::<Button>(&ctx)
+        .author_id(UserId(2))
+        .report_unknown(true)
+        .timeout(Duration::from_millis(50))
+        .build()
+        .await
+        .map(|(b, mci)| (b.id(), mci.user.id))
+        .collect::<Vec<_>>()
+        .await;
+
+    assert_eq!(events, vec![(Button::Undo.id(), UserId(2))]);
+    // The click of the other user and the unknown id got an error
+    let requests = mock.requests();
+    assert_eq!(requests.len(), 2);
+    assert!(requests.iter().all(|r| r.body["data"]["flags"] == 64));
+}
+
+#[tokio::test]
+async fn reply_prompt() {
+    let mock = MockDiscord::start().await;
+    let ctx = mock.context();
+    let mut config = ReplyPromptConfig::default();
+    config.retries(1).delete_reply(true);
+
+    mock.inject_message(user_message(10, 1, 3, "not from the user"));
+    mock.inject_message(user_message(10, 2, 2, "many"));
+    mock.inject_message(user_message(10, 3, 2, "12"));
+    let parsed = ChannelId(10)
+        .await_reply_with(&ctx, UserId(2), "How many?", &config, |m| {
+            m.content.parse::<u32>().map_err(|e| e.to_string())
+        })
+        .await;
+
+    assert_eq!(parsed.unwrap(), 12);
+    let requests = mock.requests();
+    let paths = requests
+        .iter()
+        .map(|r| (r.method.as_str(), r.path.as_str()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        paths,
+        vec![
+            ("POST", "/channels/10/messages"),
+            ("DELETE", "/channels/10/messages/2"),
+            ("POST", "/channels/10/messages"),
+            ("DELETE", "/channels/10/messages/3"),
+        ]
+    );
+    let error = &requests[2].body["embeds"][0]["fields"][0];
+    assert_eq!(error["value"], "invalid digit found in string");
+}
+
+#[tokio::test]
+async fn attachment_prompt() {
+    let mock = MockDiscord::start().await;
+    let ctx = mock.context();
+    let mut filter = AttachmentFilter::default();
+    filter.extension("csv").max_size(1024).download(true);
+
+    let mut too_large = user_message(10, 1, 2, "");
+    too_large
+        .attachments
+        .push(mock.attachment(1, "big.csv", "text/csv", 2048));
+    let mut upload = user_message(10, 2, 2, "");
+    upload
+        .attachments
+        .push(mock.attachment(2, "data.csv", "text/csv", 100));
+    mock.inject_message(too_large);
+    mock.inject_message(upload);
+    let files = ChannelId(10)
+        .await_attachments(
+            &ctx,
+            UserId(2),
+            "Upload the import",
+            &ReplyPromptConfig::default(),
+            &filter,
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(files.len(), 1);
+    assert_eq!(files[0].attachment.filename, "data.csv");
+    assert_eq!(files[0].data.as_deref(), Some(&b"data.csv"[..]));
+    let error = &mock.requests()[1].body["embeds"][0]["fields"][0];
+    assert_eq!(error["value"], "big.csv is larger than 1 KB");
+}
+
+#[tokio::test]
+async fn reaction_paginator() {
+    let mock = MockDiscord::start().await;
+    let ctx = mock.context();
+    let msg = message(10, 20);
+    let pages = ["a", "b"].map(CreateEmbed::info_box);
+
+    mock.inject_reaction(reaction(&msg, 3, "⏩", true));
+    mock.inject_reaction(reaction(&msg, 2, "⏩", true));
+    mock.inject_reaction(reaction(&msg, 2, "⏩", false));
+    mock.inject_reaction(reaction(&msg, 2, "❌", true));
+    UpdatAbleMessage::Message(msg)
+        .reaction_paginator(&ctx, UserId(2), &pages, &ReactionConfig::default())
+        .await
+        .unwrap();
+
+    let requests = mock.requests();
+    let footers = requests
+        .iter()
+        .filter(|r| r.method == Method::PATCH)
+        .map(|r| r.body["embeds"][0]["footer"]["text"].clone())
+        .collect::<Vec<_>>();
+    assert_eq!(footers, vec!["Page 1/2", "Page 2/2", "Page 1/2"]);
+    let reacted = requests.iter().filter(|r| r.method == Method::PUT).count();
+    assert_eq!(reacted, 3);
+    let last = requests.last().unwrap();
+    assert_eq!(last.method, Method::DELETE);
+    assert_eq!(last.path, "/channels/10/messages/20/reactions");
+}
+
+#[tokio::test]
+async fn quick_info() {
+    let mock = MockDiscord::start().await;
+    let ctx = mock.context();
+    let mci = component_interaction(&message(10, 20), 2, "some_button", &[]);
+
+    mci.create_quick_info(&ctx, "hello", true).await.unwrap();
+
+    let requests = mock.requests();
+    assert_eq!(requests.len(), 1);
+    let data = &requests[0].body["data"];
+    assert_eq!(data["embeds"][0]["fields"][0]["value"], "hello");
+    assert_eq!(data["flags"], 64);
+}
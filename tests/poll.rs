@@ -0,0 +1,61 @@
+use std::time::Duration;
+
+use hyper::Method;
+use serde_json::json;
+use serenity_tools::{
+    collectors::{ExpiryBehaviour, PollConfig, UpdatAbleMessage},
+    testing::{component_interaction, message, MockDiscord},
+};
+use tokio::time::sleep;
+
+#[tokio::test]
+async fn poll_expiry() {
+    let mock = MockDiscord::start().await;
+    let ctx = mock.context();
+
+    let mut config = PollConfig::default();
+    config
+        .duration(Duration::from_millis(50))
+        .expiry(ExpiryBehaviour::RemoveComponents);
+    UpdatAbleMessage::Message(message(10, 21))
+        .poll(&ctx, config, &["yes", "no"])
+        .await
+        .unwrap();
+    let requests = mock.requests();
+    assert_eq!(requests.len(), 2);
+    assert_eq!(requests[1].body["components"], json!([]));
+    let status = &requests[1].body["embeds"][0]["fields"][2]["value"];
+    assert!(status.as_str().unwrap().ends_with("closed"));
+}
+
+#[tokio::test]
+async fn poll_refresh() {
+    let mock = MockDiscord::start().await;
+    let ctx = mock.context();
+    let msg = message(10, 20);
+    let mut config = PollConfig::default();
+    config
+        .duration(Duration::from_millis(400))
+        .refresh(Duration::from_millis(50));
+
+    let poll = async {
+        UpdatAbleMessage::Message(msg.clone())
+            .poll(&ctx, config, &["yes", "no"])
+            .await
+    };
+    // Votes after a quiet period, while ticks were missed
+    let votes = async {
+        sleep(Duration::from_millis(250)).await;
+        for user in 2..7 {
+            mock.inject(component_interaction(&msg, user, "_tools_poll_0", &[]));
+            sleep(Duration::from_millis(5)).await;
+        }
+    };
+    let (result, _) = tokio::join!(poll, votes);
+
+    assert_eq!(result.unwrap().counts(), vec![5, 0]);
+    let requests = mock.requests();
+    let edits = requests.iter().filter(|r| r.method == Method::PATCH);
+    // The first and the closing render and at most two for the votes
+    assert!(edits.count() <= 4);
+}
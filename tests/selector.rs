@@ -0,0 +1,396 @@
+mod common;
+
+use std::{
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use hyper::Method;
+use serde_json::json;
+use serenity::builder::CreateEmbed;
+use serenity_tools::{
+    builder::CreateEmbedExt,
+    collectors::{
+        resume_sessions, CancellationToken, ExpiryBehaviour, MemorySessionStore,
+        PagedSelectorConfig, PagedSelectorError, PagedSelectorState, SessionStore, StoredSession,
+        UpdatAbleMessage, UpdateScheduler,
+    },
+    components::Button,
+    testing::{component_interaction, message, MockDiscord},
+};
+use tokio::time::timeout;
+
+use common::button;
+
+#[tokio::test]
+async fn paged_selector() {
+    let mock = MockDiscord::start().await;
+    let ctx = mock.context();
+    let msg = message(10, 20);
+    let values = vec![1, 2, 3];
+
+    let selector = async {
+        let mut msg = UpdatAbleMessage::Message(msg.clone());
+        msg.paged_selector(&ctx, PagedSelectorConfig::default(), &values, button)
+            .await
+    };
+    let clicks = async {
+        mock.wait_for_requests(1).await;
+        mock.inject(component_interaction(&msg, 2, "_tools_selector_1", &[]));
+        mock.inject(component_interaction(&msg, 2, Button::Confirm.id(), &[]));
+    };
+    let (selected, _) = tokio::join!(selector, clicks);
+
+    assert_eq!(selected.unwrap().into_iter().collect::<Vec<_>>(), vec![&2]);
+    let requests = mock.requests();
+    assert_eq!(requests[0].method, Method::PATCH);
+    assert_eq!(requests[0].path, "/channels/10/messages/20");
+    assert!(requests[1].path.ends_with("/callback"));
+}
+
+#[tokio::test]
+async fn click_responses() {
+    let mock = MockDiscord::start().await;
+    let ctx = mock.context();
+    let msg = message(10, 20);
+
+    let config = PagedSelectorConfig::default();
+    let mut limits = config.limits();
+    limits.edit_interval(Duration::ZERO);
+
+    let selector = async {
+        let mut msg = UpdatAbleMessage::Message(msg.clone());
+        let state = PagedSelectorState::new(config, &[1, 2, 3], button);
+        msg.run_selector(&ctx, state, &limits).await
+    };
+    let clicks = async {
+        mock.wait_for_requests(1).await;
+        for id in [
+            "_tools_selector_0",
+            "_tools_selector_1",
+            "_tools_selector_0",
+        ] {
+            mock.inject(component_interaction(&msg, 2, id, &[]));
+        }
+        mock.wait_for_requests(4).await;
+        mock.inject(component_interaction(&msg, 2, Button::Confirm.id(), &[]));
+    };
+    let (selected, _) = tokio::join!(selector, clicks);
+
+    assert_eq!(selected.unwrap().into_iter().collect::<Vec<_>>(), vec![&2]);
+    // Every click is answered with the new state instead of an edit
+    let requests = mock.requests();
+    let edits = requests.iter().filter(|r| r.method == Method::PATCH);
+    assert_eq!(edits.count(), 1);
+    let update = &requests[3].body;
+    assert_eq!(update["type"], 7);
+    assert_eq!(
+        update["data"]["embeds"][0]["fields"][0]["value"],
+        "⬛ | 1\n✅ | 2\n⬛ | 3"
+    );
+}
+
+#[tokio::test]
+async fn coalesced_edits() {
+    let mock = MockDiscord::start().await;
+    let ctx = mock.context();
+    let mut msg = UpdatAbleMessage::Message(message(10, 20));
+    let mut updates = UpdateScheduler::new(Duration::ZERO);
+
+    // Only the latest render gets sent
+    updates.schedule(vec![CreateEmbed::info_box("a")], Vec::new());
+    updates.schedule(vec![CreateEmbed::info_box("b")], Vec::new());
+    assert!(updates.flush(&ctx, &mut msg).await.unwrap());
+    // The same render again is skipped
+    updates.schedule(vec![CreateEmbed::info_box("b")], Vec::new());
+    assert!(!updates.flush(&ctx, &mut msg).await.unwrap());
+    assert!(!updates.is_pending());
+
+    let requests = mock.requests();
+    assert_eq!(requests.len(), 1);
+    assert_eq!(requests[0].body["embeds"][0]["fields"][0]["value"], "b");
+}
+
+#[tokio::test]
+async fn coalesced_clicks() {
+    let mock = MockDiscord::start().await;
+    let ctx = mock.context();
+    let msg = message(10, 20);
+
+    let selector = async {
+        let mut msg = UpdatAbleMessage::Message(msg.clone());
+        msg.paged_selector(&ctx, PagedSelectorConfig::default(), &[1, 2, 3], button)
+            .await
+    };
+    // Clicks within the edit interval are only acknowledged
+    let clicks = async {
+        mock.wait_for_requests(1).await;
+        for id in ["_tools_selector_0", "_tools_selector_1"] {
+            mock.inject(component_interaction(&msg, 2, id, &[]));
+        }
+        mock.wait_for_requests(4).await;
+        mock.inject(component_interaction(&msg, 2, Button::Confirm.id(), &[]));
+    };
+    let (selected, _) = tokio::join!(selector, clicks);
+
+    assert_eq!(selected.unwrap().len(), 2);
+    let requests = mock.requests();
+    assert_eq!(requests[1].body["type"], 6);
+    assert_eq!(requests[2].body["type"], 6);
+    assert_eq!(requests[3].method, Method::PATCH);
+    assert_eq!(
+        requests[3].body["embeds"][0]["fields"][0]["value"],
+        "✅ | 1\n✅ | 2\n⬛ | 3"
+    );
+}
+
+#[tokio::test]
+async fn failed_click_response() {
+    let mock = MockDiscord::start().await;
+    let ctx = mock.context();
+    let msg = message(10, 20);
+    mock.fail_requests(|r| r.path.ends_with("/callback") && r.body["type"] == 7);
+    let config = PagedSelectorConfig::default();
+    let mut limits = config.limits();
+    limits.edit_interval(Duration::ZERO);
+
+    let selector = async {
+        let mut msg = UpdatAbleMessage::Message(msg.clone());
+        let state = PagedSelectorState::new(config, &[1, 2, 3], button);
+        msg.run_selector(&ctx, state, &limits).await
+    };
+    let clicks = async {
+        mock.wait_for_requests(1).await;
+        mock.inject(component_interaction(&msg, 2, "_tools_selector_0", &[]));
+        mock.wait_for_requests(4).await;
+        mock.inject(component_interaction(&msg, 2, Button::Confirm.id(), &[]));
+    };
+    let (selected, _) = tokio::join!(selector, clicks);
+
+    assert_eq!(selected.unwrap().len(), 1);
+    // The click still gets acknowledged and the render sent as edit
+    let requests = mock.requests();
+    assert_eq!(requests[1].body["type"], 7);
+    assert_eq!(requests[2].body["type"], 6);
+    assert_eq!(requests[3].method, Method::PATCH);
+    assert_eq!(
+        requests[3].body["embeds"][0]["fields"][0]["value"],
+        "✅ | 1\n⬛ | 2\n⬛ | 3"
+    );
+}
+
+#[tokio::test]
+async fn acknowledged_click() {
+    let mock = MockDiscord::start().await;
+    let ctx = mock.context();
+    let msg = message(10, 20);
+    let config = PagedSelectorConfig::default();
+    let mut limits = config.limits();
+    limits.edit_interval(Duration::ZERO);
+
+    // The update reached Discord but its response got lost
+    let click = component_interaction(&msg, 2, "_tools_selector_0", &[]);
+    let callback = format!("/interactions/{}/", click.id);
+    mock.fail_requests_with(40060, move |r| r.path.starts_with(&callback));
+
+    let selector = async {
+        let mut msg = UpdatAbleMessage::Message(msg.clone());
+        let state = PagedSelectorState::new(config, &[1, 2, 3], button);
+        msg.run_selector(&ctx, state, &limits).await
+    };
+    let clicks = async {
+        mock.wait_for_requests(1).await;
+        mock.inject(click);
+        mock.wait_for_requests(4).await;
+        mock.inject(component_interaction(&msg, 2, Button::Confirm.id(), &[]));
+    };
+    let (selected, _) = tokio::join!(selector, clicks);
+
+    assert_eq!(selected.unwrap().len(), 1);
+    let requests = mock.requests();
+    assert_eq!(requests[1].body["type"], 7);
+    assert_eq!(requests[2].body["type"], 6);
+    // The render is still sent as edit
+    assert_eq!(requests[3].method, Method::PATCH);
+}
+
+#[tokio::test]
+async fn cancelled_selector() {
+    let mock = MockDiscord::start().await;
+    let ctx = mock.context();
+    let msg = message(10, 20);
+    let token = CancellationToken::new();
+    let store: Arc<dyn SessionStore> = Arc::new(MemorySessionStore::default());
+    let mut config = PagedSelectorConfig::default();
+    config
+        .cancel_token(token.clone())
+        .persist(store.clone(), "numbers");
+
+    let selector = async {
+        let mut msg = UpdatAbleMessage::Message(msg.clone());
+        msg.paged_selector(&ctx, config, &[1, 2, 3], button).await
+    };
+    let cancel = async {
+        mock.wait_for_requests(1).await;
+        token.cancel();
+    };
+    let (selected, _) = tokio::join!(selector, cancel);
+
+    assert!(matches!(selected, Err(PagedSelectorError::Cancelled)));
+    let requests = mock.requests();
+    assert_eq!(requests.len(), 2);
+    assert_eq!(requests[1].body["components"], json!([]));
+    // Cancelling on shutdown must not lose the progress
+    assert_eq!(store.load_all().unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn interaction_limit() {
+    let mock = MockDiscord::start().await;
+    let ctx = mock.context();
+    let msg = message(10, 20);
+    let mut config = PagedSelectorConfig::default();
+    config.max_interactions(1).deadline(Duration::from_secs(60));
+
+    let selector = async {
+        let mut msg = UpdatAbleMessage::Message(msg.clone());
+        msg.paged_selector(&ctx, config, &[1, 2, 3], button).await
+    };
+    let clicks = async {
+        mock.wait_for_requests(1).await;
+        mock.inject(component_interaction(&msg, 2, "_tools_selector_0", &[]));
+    };
+    let (selected, _) = tokio::join!(selector, clicks);
+
+    assert!(matches!(
+        selected,
+        Err(PagedSelectorError::InteractionLimitReached)
+    ));
+    // The expired message keeps its components disabled
+    let requests = mock.wait_for_requests(3).await;
+    let row = &requests[2].body["components"][0];
+    assert_eq!(row["components"][0]["disabled"], true);
+}
+
+#[tokio::test]
+async fn selector_expiry() {
+    let mock = MockDiscord::start().await;
+    let ctx = mock.context();
+    let msg = message(10, 20);
+    let run = |expiry: ExpiryBehaviour| {
+        let mut config = PagedSelectorConfig::default();
+        config
+            .timeout(Duration::from_millis(50))
+            .expiry(expiry)
+            .show_expiry(true);
+        let mut msg = UpdatAbleMessage::Message(msg.clone());
+        let ctx = ctx.clone();
+        async move {
+            msg.paged_selector(&ctx, config, &[1, 2, 3], button)
+                .await
+                .map(|s| s.len())
+        }
+    };
+
+    let res = run(ExpiryBehaviour::RemoveComponents).await;
+    assert!(matches!(res, Err(PagedSelectorError::TimedOut)));
+    let requests = mock.requests();
+    let expires = requests[0].body["embeds"][0]["fields"][1]["value"].clone();
+    assert!(expires.as_str().unwrap().starts_with("expires <t:"));
+    // The embed stays, only the components are gone
+    assert_eq!(requests[1].body["embeds"], requests[0].body["embeds"]);
+    assert_eq!(requests[1].body["components"], json!([]));
+
+    mock.clear_requests();
+    let res = run(ExpiryBehaviour::InfoBox("Too slow".to_string())).await;
+    assert!(matches!(res, Err(PagedSelectorError::TimedOut)));
+    let requests = mock.requests();
+    assert_eq!(
+        requests[1].body["embeds"][0]["fields"][0]["value"],
+        "Too slow"
+    );
+    assert_eq!(requests[1].body["components"], json!([]));
+}
+
+#[tokio::test]
+async fn resumed_selector() {
+    let mock = MockDiscord::start().await;
+    let ctx = mock.context();
+    let msg = message(10, 20);
+    let store: Arc<dyn SessionStore> = Arc::new(MemorySessionStore::default());
+    let mut config = PagedSelectorConfig::default();
+    config.persist(store.clone(), "numbers");
+
+    // The first run stops like on a restart after one click
+    let selector = async {
+        let mut msg = UpdatAbleMessage::Message(msg.clone());
+        msg.paged_selector(&ctx, config.clone(), &[1, 2, 3], button)
+            .await
+    };
+    let click = async {
+        mock.wait_for_requests(1).await;
+        mock.inject(component_interaction(&msg, 2, "_tools_selector_1", &[]));
+        mock.wait_for_requests(2).await;
+    };
+    tokio::select! {
+        _ = selector => panic!("Selector should still run"),
+        _ = click => (),
+    }
+
+    let resumed = resume_sessions(&ctx, store.clone(), |_, _, session| async move {
+        assert_eq!(session.kind, "numbers");
+    })
+    .await
+    .unwrap();
+    assert_eq!(resumed, 1);
+
+    let selector = async {
+        let mut msg = UpdatAbleMessage::Message(msg.clone());
+        msg.paged_selector(&ctx, config.clone(), &[1, 2, 3], button)
+            .await
+    };
+    mock.inject(component_interaction(&msg, 2, Button::Confirm.id(), &[]));
+    let selected = selector.await.unwrap();
+
+    assert_eq!(selected.into_iter().collect::<Vec<_>>(), vec![&2]);
+    assert!(store.load_all().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn resumed_deadline() {
+    let mock = MockDiscord::start().await;
+    let ctx = mock.context();
+    let msg = message(10, 20);
+    let store: Arc<dyn SessionStore> = Arc::new(MemorySessionStore::default());
+    let mut config = PagedSelectorConfig::default();
+    config
+        .deadline(Duration::from_secs(60))
+        .persist(store.clone(), "numbers");
+
+    // Stored by a run whose deadline passes shortly
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+    store
+        .save(&StoredSession {
+            message_id: msg.id,
+            channel_id: msg.channel_id,
+            kind: "numbers".to_string(),
+            state: json!({
+                "state": { "page": 0, "selected": [1] },
+                "deadline_at": (now + Duration::from_millis(50)).as_millis() as u64,
+            }),
+        })
+        .unwrap();
+    let selector = async {
+        let mut msg = UpdatAbleMessage::Message(msg.clone());
+        msg.paged_selector(&ctx, config, &[1, 2, 3], button).await
+    };
+    let res = timeout(Duration::from_secs(5), selector).await.unwrap();
+
+    assert!(matches!(res, Err(PagedSelectorError::DeadlineExceeded)));
+    // The restored selection was shown
+    let requests = mock.requests();
+    assert_eq!(
+        requests[0].body["embeds"][0]["fields"][0]["value"],
+        "⬛ | 1\n✅ | 2\n⬛ | 3"
+    );
+}
@@ -0,0 +1,43 @@
+use serenity::builder::{CreateComponents, CreateEmbed};
+use serenity_tools::{
+    assert_snapshot,
+    builder::{CreateComponentsExt, CreateEmbedExt},
+    components::Button,
+};
+
+#[test]
+fn embed_snapshots() {
+    assert_snapshot!("info_box", CreateEmbed::info_box("info"));
+    assert_snapshot!("error_box", CreateEmbed::error_box("error"));
+    assert_snapshot!("success_box", CreateEmbed::success_box("success"));
+
+    let mut e = CreateEmbed::default();
+    e.fields_chunked(&[1, 2, 3, 4, 5], "Numbers", true, 2);
+    assert_snapshot!("fields_chunked", e);
+}
+
+#[test]
+fn component_snapshots() {
+    let buttons = [
+        Button::Confirm,
+        Button::Abort,
+        Button::Next,
+        Button::Previous,
+        Button::SelectPage,
+        Button::SelectAll,
+        Button::ClearAll,
+        Button::Invert,
+        Button::Undo,
+        Button::Decrease,
+        Button::Increase,
+        Button::Leave,
+    ];
+    assert_snapshot!(
+        "buttons",
+        buttons.iter().map(Button::create).collect::<Vec<_>>()
+    );
+
+    let mut c = CreateComponents::default();
+    c.confirm_abort_row();
+    assert_snapshot!("confirm_abort_row", c);
+}
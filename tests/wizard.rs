@@ -0,0 +1,118 @@
+use std::time::Duration;
+
+use serenity::model::application::component::InputTextStyle;
+use serenity_tools::{
+    collectors::{UpdatAbleMessage, Wizard, WizardError},
+    components::Button,
+    testing::{component_interaction, message, modal_submit, MockDiscord},
+};
+
+#[tokio::test]
+async fn wizard_expiry() {
+    let mock = MockDiscord::start().await;
+    let ctx = mock.context();
+
+    let mut wizard = Wizard::default();
+    wizard
+        .timeout(Duration::from_millis(50))
+        .show_expiry(true)
+        .choice("size", "Size", vec!["S".to_string(), "L".to_string()]);
+    let res = wizard
+        .run(
+            &ctx,
+            &mut UpdatAbleMessage::Message(message(10, 20)),
+            |_| Ok(()),
+        )
+        .await;
+    assert!(matches!(res, Err(WizardError::TimedOut)));
+    let requests = mock.requests();
+    let expires = requests[0].body["embeds"][0]["fields"][1]["value"].clone();
+    assert!(expires.as_str().unwrap().starts_with("expires <t:"));
+    // Timed out wizards get their components disabled like selectors
+    let row = &requests[1].body["components"][0];
+    assert_eq!(row["components"][0]["disabled"], true);
+}
+
+#[tokio::test]
+async fn optional_select() {
+    let mock = MockDiscord::start().await;
+    let ctx = mock.context();
+    let msg = message(10, 20);
+    let options = vec!["a".to_string(), "b".to_string()];
+
+    let mut wizard = Wizard::default();
+    wizard
+        .select("tags", "Tags", options.clone(), 0, 2)
+        // min above max gets lowered
+        .select("pick", "Pick", options, 3, 1);
+    let run = async {
+        let mut msg = UpdatAbleMessage::Message(msg.clone());
+        wizard
+            .run(&ctx, &mut msg, |a| {
+                Ok((
+                    a.selected("tags").map(<[usize]>::to_vec),
+                    a.selected("pick").map(<[usize]>::to_vec),
+                ))
+            })
+            .await
+    };
+    let clicks = async {
+        mock.wait_for_requests(1).await;
+        // Leaving the select empty is allowed
+        mock.inject(component_interaction(&msg, 2, Button::Next.id(), &[]));
+        mock.wait_for_requests(3).await;
+        mock.inject(component_interaction(
+            &msg,
+            2,
+            "_tools_wizard_select",
+            &["1"],
+        ));
+        mock.wait_for_requests(5).await;
+        mock.inject(component_interaction(&msg, 2, Button::Next.id(), &[]));
+    };
+    let (res, _) = tokio::join!(run, clicks);
+
+    assert_eq!(res.unwrap(), (Some(vec![]), Some(vec![1])));
+    let requests = mock.requests();
+    // Next is the last button of the navigation row
+    let next = |r: usize| {
+        let nav = requests[r].body["components"][1]["components"].clone();
+        nav.as_array().unwrap().last().unwrap()["disabled"].clone()
+    };
+    assert_eq!(next(0), false);
+    let menu = &requests[2].body["components"][0]["components"][0];
+    assert_eq!(menu["min_values"], 1);
+    assert_eq!(menu["max_values"], 1);
+    assert_eq!(next(2), true);
+}
+
+#[tokio::test]
+async fn input_step() {
+    let mock = MockDiscord::start().await;
+    let ctx = mock.context();
+    let msg = message(10, 20);
+
+    let mut wizard = Wizard::default();
+    wizard.input("name", "Name", "Your name", InputTextStyle::Short);
+    let run = async {
+        let mut msg = UpdatAbleMessage::Message(msg.clone());
+        wizard
+            .run(&ctx, &mut msg, |a| Ok(a.input("name").unwrap().to_string()))
+            .await
+    };
+    let inputs = async {
+        mock.wait_for_requests(1).await;
+        mock.inject(component_interaction(&msg, 2, "_tools_wizard_input", &[]));
+        mock.wait_for_requests(2).await;
+        let input = [("_tools_wizard_input", "Ferris")];
+        mock.inject_modal(modal_submit(&msg, 2, "_tools_wizard_modal", &input));
+    };
+    let (res, _) = tokio::join!(run, inputs);
+
+    assert_eq!(res.unwrap(), "Ferris");
+    let requests = mock.requests();
+    // The modal is opened as response to the click
+    assert_eq!(requests[1].body["type"], 9);
+    assert_eq!(requests[1].body["data"]["custom_id"], "_tools_wizard_modal");
+    assert_eq!(requests[2].body["type"], 6);
+}